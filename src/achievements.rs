@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+use std::fs;
+
+use crate::enemy::EnemyType;
+use crate::events::{GameEvent, Subscriber};
+use crate::save::{self, write_file};
+use crate::settings::parse_key_values;
+
+pub const ACHIEVEMENTS_PATH: &str = "assets/achievements.txt";
+// the unlocked ids, kept in the profile's folder, see profile.rs
+pub const UNLOCKED_FILE: &str = "achievements.txt";
+
+// What has to happen for an achievement to unlock, see assets/achievements.txt
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Condition {
+    Kills {
+        enemy: Option<EnemyType>,
+        target: u32,
+    },
+    KillsNoMiss {
+        enemy: Option<EnemyType>,
+        target: u32,
+    },
+    ReachWave(u32),
+    WaveWithoutFiring,
+    Combo(i32),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Achievement {
+    pub id: String,
+    condition: Condition,
+}
+
+// the `key = value` lines of one `[id]` section
+#[derive(Default)]
+struct Section<'t> {
+    condition: &'t str,
+    enemy: Option<&'t str>,
+    target: u32,
+}
+
+impl Section<'_> {
+    fn condition(&self) -> Option<Condition> {
+        let enemy = match self.enemy {
+            Some(name) => Some(EnemyType::from_name(name)?),
+            None => None,
+        };
+        Some(match self.condition {
+            "kills" => Condition::Kills {
+                enemy,
+                target: self.target,
+            },
+            "kills_no_miss" => Condition::KillsNoMiss {
+                enemy,
+                target: self.target,
+            },
+            "reach_wave" => Condition::ReachWave(self.target),
+            "wave_without_firing" => Condition::WaveWithoutFiring,
+            "combo" => Condition::Combo(self.target as i32),
+            _ => return None,
+        })
+    }
+}
+
+// How far the current run has got towards the achievements, so a suspended
+// run can carry on where it was; counters are by id and only the non-zero ones
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct RunProgress {
+    pub counters: Vec<(String, u32)>,
+    pub fired_this_wave: bool,
+}
+
+// The achievement list, which ones are unlocked, and the progress towards the
+// rest during the current run
+pub struct Achievements {
+    list: Vec<Achievement>,
+    unlocked: HashSet<String>,
+    // unlocked since the last take_new_unlocks, for the toast
+    new_unlocks: Vec<String>,
+    // one counter per achievement, reset every run
+    progress: Vec<u32>,
+    fired_this_wave: bool,
+}
+
+impl Achievements {
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path, e);
+            String::new()
+        });
+        Achievements::parse(&text)
+    }
+
+    // `[id]` sections of `key = value` lines; a section with an unknown
+    // condition or enemy is reported and left out
+    fn parse(text: &str) -> Self {
+        let mut sections: Vec<(&str, Section)> = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if let Some(id) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                sections.push((id, Section::default()));
+                continue;
+            }
+            let Some((_, section)) = sections.last_mut() else {
+                continue;
+            };
+            for (key, value) in parse_key_values(line) {
+                match key {
+                    "condition" => section.condition = value,
+                    "enemy" => section.enemy = Some(value),
+                    "target" => section.target = value.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+        }
+
+        let list: Vec<Achievement> = sections
+            .into_iter()
+            .filter_map(|(id, section)| match section.condition() {
+                Some(condition) => Some(Achievement {
+                    id: id.to_string(),
+                    condition,
+                }),
+                None => {
+                    eprintln!("Achievement {} has an unknown condition", id);
+                    None
+                }
+            })
+            .collect();
+        Achievements {
+            progress: vec![0; list.len()],
+            list,
+            unlocked: HashSet::new(),
+            new_unlocks: Vec::new(),
+            fired_this_wave: false,
+        }
+    }
+
+    // one unlocked id per line
+    pub fn load_unlocked(&mut self, path: &str) {
+        let text = save::read(path, |_| true).unwrap_or_default();
+        self.unlocked = text
+            .lines()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+
+    pub fn save_unlocked(&self, path: &str) {
+        let mut ids: Vec<&str> = self.unlocked.iter().map(String::as_str).collect();
+        ids.sort();
+        let mut text = ids.join("\n");
+        text.push('\n');
+        write_file(path, &text);
+    }
+
+    pub fn start_run(&mut self) {
+        self.progress.iter_mut().for_each(|p| *p = 0);
+        self.fired_this_wave = false;
+    }
+
+    pub fn run_progress(&self) -> RunProgress {
+        RunProgress {
+            counters: self
+                .list
+                .iter()
+                .zip(&self.progress)
+                .filter(|(_, progress)| **progress > 0)
+                .map(|(achievement, progress)| (achievement.id.clone(), *progress))
+                .collect(),
+            fired_this_wave: self.fired_this_wave,
+        }
+    }
+
+    // instead of start_run when a suspended run is continued; counters for
+    // achievements that aren't in the list any more are dropped
+    pub fn resume_run(&mut self, run: &RunProgress) {
+        self.start_run();
+        for (id, count) in &run.counters {
+            if let Some(i) = self.list.iter().position(|a| a.id == *id) {
+                self.progress[i] = *count;
+            }
+        }
+        self.fired_this_wave = run.fired_this_wave;
+    }
+
+    pub fn list(&self) -> &[Achievement] {
+        &self.list
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    // ids unlocked since the last call, in the order they happened
+    pub fn take_new_unlocks(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.new_unlocks)
+    }
+
+    fn unlock(&mut self, index: usize) {
+        let id = &self.list[index].id;
+        if self.unlocked.insert(id.clone()) {
+            self.new_unlocks.push(id.clone());
+        }
+    }
+}
+
+impl Subscriber for Achievements {
+    fn on_event(&mut self, event: &GameEvent) {
+        for i in 0..self.list.len() {
+            let progress = &mut self.progress[i];
+            let done = match (self.list[i].condition, *event) {
+                (
+                    Condition::Kills { enemy, target } | Condition::KillsNoMiss { enemy, target },
+                    GameEvent::EnemyKilled { enemy_type, .. },
+                ) => {
+                    if enemy.is_none_or(|e| e == enemy_type) {
+                        *progress += 1;
+                    }
+                    *progress >= target
+                }
+                (Condition::KillsNoMiss { .. }, GameEvent::ShotMissed) => {
+                    *progress = 0;
+                    false
+                }
+                (Condition::ReachWave(target), GameEvent::WaveCleared { wave_number }) => {
+                    wave_number + 1 >= target
+                }
+                (Condition::WaveWithoutFiring, GameEvent::WaveCleared { .. }) => {
+                    !self.fired_this_wave
+                }
+                (Condition::Combo(target), GameEvent::EnemyKilled { chain, .. }) => chain >= target,
+                _ => false,
+            };
+            if done {
+                self.unlock(i);
+            }
+        }
+
+        match event {
+            GameEvent::ShotFired { .. } => self.fired_this_wave = true,
+            GameEvent::WaveCleared { .. } => self.fired_this_wave = false,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sfml::system::Vector2f;
+
+    const HERE: Vector2f = Vector2f::new(0.0, 0.0);
+
+    fn kill(enemy_type: EnemyType, chain: i32) -> GameEvent {
+        GameEvent::EnemyKilled {
+            enemy_type,
+            pos: HERE,
+            angle: 0.0,
+            points: 100,
+            chain,
+        }
+    }
+
+    fn with(text: &str) -> Achievements {
+        let mut achievements = Achievements::parse(text);
+        achievements.start_run();
+        achievements
+    }
+
+    #[test]
+    fn the_shipped_list_parses_completely() {
+        let text = include_str!("../assets/achievements.txt");
+        let sections = text.lines().filter(|l| l.starts_with('[')).count();
+        assert_eq!(Achievements::parse(text).list().len(), sections);
+    }
+
+    #[test]
+    fn unknown_conditions_and_enemies_are_left_out() {
+        let achievements = with(
+            "[a]\ncondition = dance\n[b]\ncondition = kills\nenemy = dragon\n[c]\ncondition = combo\ntarget = 3",
+        );
+        let ids: Vec<&str> = achievements.list().iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["c"]);
+    }
+
+    #[test]
+    fn kills_only_count_the_named_enemy() {
+        let mut achievements = with("[tanks]\ncondition = kills\nenemy = armored\ntarget = 2");
+        achievements.on_event(&kill(EnemyType::Fast, 1));
+        achievements.on_event(&kill(EnemyType::Armored, 2));
+        assert!(achievements.take_new_unlocks().is_empty());
+        achievements.on_event(&kill(EnemyType::Armored, 3));
+        assert_eq!(achievements.take_new_unlocks(), vec!["tanks"]);
+        assert!(achievements.is_unlocked("tanks"));
+    }
+
+    #[test]
+    fn a_miss_resets_a_no_miss_streak() {
+        let mut achievements =
+            with("[clean]\ncondition = kills_no_miss\nenemy = armored\ntarget = 2");
+        achievements.on_event(&kill(EnemyType::Armored, 1));
+        achievements.on_event(&GameEvent::ShotMissed);
+        achievements.on_event(&kill(EnemyType::Armored, 2));
+        assert!(!achievements.is_unlocked("clean"));
+        achievements.on_event(&kill(EnemyType::Armored, 3));
+        assert!(achievements.is_unlocked("clean"));
+    }
+
+    #[test]
+    fn clearing_a_wave_without_firing() {
+        let mut achievements = with("[calm]\ncondition = wave_without_firing");
+        achievements.on_event(&GameEvent::ShotFired { pos: HERE });
+        achievements.on_event(&GameEvent::WaveCleared { wave_number: 0 });
+        assert!(!achievements.is_unlocked("calm"));
+        achievements.on_event(&GameEvent::WaveCleared { wave_number: 1 });
+        assert!(achievements.is_unlocked("calm"));
+    }
+
+    #[test]
+    fn waves_count_from_one() {
+        let mut achievements = with("[far]\ncondition = reach_wave\ntarget = 3");
+        achievements.on_event(&GameEvent::WaveCleared { wave_number: 1 });
+        assert!(!achievements.is_unlocked("far"));
+        achievements.on_event(&GameEvent::WaveCleared { wave_number: 2 });
+        assert!(achievements.is_unlocked("far"));
+    }
+
+    #[test]
+    fn progress_starts_over_every_run_but_unlocks_stay() {
+        let mut achievements =
+            with("[two]\ncondition = kills\ntarget = 2\n[one]\ncondition = kills\ntarget = 1");
+        achievements.on_event(&kill(EnemyType::Default, 1));
+        achievements.start_run();
+        achievements.on_event(&kill(EnemyType::Default, 1));
+        assert!(!achievements.is_unlocked("two"));
+        assert!(achievements.is_unlocked("one"));
+        // unlocking again doesn't toast again
+        assert_eq!(achievements.take_new_unlocks(), vec!["one"]);
+    }
+
+    #[test]
+    fn a_continued_run_carries_on_with_its_progress() {
+        let text = "[two]\ncondition = kills\ntarget = 2\n[calm]\ncondition = wave_without_firing";
+        let mut achievements = with(text);
+        achievements.on_event(&kill(EnemyType::Default, 1));
+        achievements.on_event(&GameEvent::ShotFired { pos: HERE });
+        let saved = achievements.run_progress();
+
+        let mut achievements = with(text);
+        achievements.resume_run(&saved);
+        achievements.on_event(&GameEvent::WaveCleared { wave_number: 0 });
+        assert!(!achievements.is_unlocked("calm"));
+        achievements.on_event(&kill(EnemyType::Default, 1));
+        assert!(achievements.is_unlocked("two"));
+    }
+}
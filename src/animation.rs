@@ -0,0 +1,367 @@
+use sfml::cpp::FBox;
+use sfml::graphics::{IntRect, Sprite, Texture, Transformable};
+use sfml::system::{Vector2f, Vector2u};
+use std::fs;
+
+use crate::settings::parse_key_values;
+
+pub const ANIMATIONS_PATH: &str = "assets/animations.txt";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // stops on the last frame and reports itself finished
+    Once,
+}
+
+// One frame: a cell of a sprite sheet shown for `duration` seconds
+pub struct Frame {
+    pub sheet: usize,
+    pub rect: IntRect,
+    pub duration: f32,
+}
+
+pub struct Animation {
+    pub name: String,
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AnimationId(usize);
+
+// All sheets and animations from assets/animations.txt. Each `[name]` section
+// is one animation:
+//
+//     [ship_bank_right]
+//     mode = once
+//     frame_size = 45, 36
+//     frame = assets/shipSheet.png 0 0.05
+//     frame = assets/shipSheet.png 2 1.0
+//
+// A frame is a sheet path, the cell index and a duration. Cells are
+// `frame_size` big and counted row by row from the top left; without a
+// `frame_size` the whole image is one cell.
+pub struct AnimationLibrary {
+    sheet_paths: Vec<String>,
+    sheets: Vec<FBox<Texture>>,
+    animations: Vec<Animation>,
+}
+
+impl AnimationLibrary {
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).expect("Failed to load animations");
+        let mut library = AnimationLibrary {
+            sheet_paths: Vec::new(),
+            sheets: Vec::new(),
+            animations: Vec::new(),
+        };
+
+        let mut frame_size: Option<(i32, i32)> = None;
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                library.animations.push(Animation {
+                    name: name.to_string(),
+                    frames: Vec::new(),
+                    mode: PlayMode::Loop,
+                });
+                frame_size = None;
+                continue;
+            }
+            for (key, value) in parse_key_values(line) {
+                match key {
+                    "mode" => {
+                        let mode = if value == "once" {
+                            PlayMode::Once
+                        } else {
+                            PlayMode::Loop
+                        };
+                        if let Some(animation) = library.animations.last_mut() {
+                            animation.mode = mode;
+                        }
+                    }
+                    "frame_size" => {
+                        frame_size = value.split_once(',').and_then(|(w, h)| {
+                            Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+                        });
+                    }
+                    "frame" => {
+                        let frame = library.parse_frame(value, frame_size);
+                        if let Some(animation) = library.animations.last_mut() {
+                            match frame {
+                                Some(frame) => animation.frames.push(frame),
+                                None => eprintln!(
+                                    "Animation {} has a bad frame, skipping it: {}",
+                                    animation.name, value
+                                ),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        library.animations.retain(|a| {
+            if a.frames.is_empty() {
+                eprintln!("Animation {} has no frames, skipping it", a.name);
+            }
+            !a.frames.is_empty()
+        });
+        library
+    }
+
+    fn parse_frame(&mut self, value: &str, frame_size: Option<(i32, i32)>) -> Option<Frame> {
+        let mut parts = value.split_whitespace();
+        let sheet = self.sheet(parts.next()?);
+        let index: i32 = parts.next()?.parse().ok()?;
+        let duration: f32 = parts.next()?.parse().ok()?;
+        Some(Frame {
+            sheet,
+            rect: cell_rect(self.sheets[sheet].size(), frame_size, index)?,
+            duration: duration.max(0.001),
+        })
+    }
+
+    // sheets are loaded once and shared by every animation that uses them
+    fn sheet(&mut self, path: &str) -> usize {
+        if let Some(i) = self.sheet_paths.iter().position(|p| p == path) {
+            return i;
+        }
+        let texture = Texture::from_file(path).expect("Failed to load sprite sheet");
+        self.sheet_paths.push(path.to_string());
+        self.sheets.push(texture);
+        self.sheets.len() - 1
+    }
+
+    // look animations up once when loading, not every time one starts playing;
+    // None if the file doesn't have it, or it had no usable frames
+    pub fn id(&self, name: &str) -> Option<AnimationId> {
+        self.animations
+            .iter()
+            .position(|a| a.name == name)
+            .map(AnimationId)
+    }
+
+    fn get(&self, id: AnimationId) -> &Animation {
+        &self.animations[id.0]
+    }
+}
+
+// Cell `index` of a sheet of `sheet_size`, or None if the cells have no size or
+// the sheet has no such cell
+fn cell_rect(sheet_size: Vector2u, frame_size: Option<(i32, i32)>, index: i32) -> Option<IntRect> {
+    let (sheet_width, sheet_height) = (sheet_size.x as i32, sheet_size.y as i32);
+    let (width, height) = frame_size.unwrap_or((sheet_width, sheet_height));
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let columns = sheet_width / width;
+    let rows = sheet_height / height;
+    if index < 0 || index >= columns * rows {
+        return None;
+    }
+    Some(IntRect::new(
+        (index % columns) * width,
+        (index / columns) * height,
+        width,
+        height,
+    ))
+}
+
+// Playback state for one sprite
+#[derive(Clone, Copy)]
+pub struct Animator {
+    animation: AnimationId,
+    frame: usize,
+    time: f32,
+    finished: bool,
+}
+
+impl Animator {
+    pub fn new(animation: AnimationId) -> Self {
+        Animator {
+            animation,
+            frame: 0,
+            time: 0.0,
+            finished: false,
+        }
+    }
+
+    // switching to the animation that is already playing doesn't restart it
+    pub fn play(&mut self, animation: AnimationId) {
+        if self.animation != animation {
+            *self = Animator::new(animation);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn update(&mut self, library: &AnimationLibrary, dt: f32) {
+        let animation = library.get(self.animation);
+        self.time += dt;
+        while !self.finished && self.time >= animation.frames[self.frame].duration {
+            self.time -= animation.frames[self.frame].duration;
+            if self.frame + 1 < animation.frames.len() {
+                self.frame += 1;
+            } else if animation.mode == PlayMode::Loop {
+                self.frame = 0;
+            } else {
+                self.finished = true;
+            }
+        }
+    }
+
+    // puts the current frame on `sprite`, drawn at `position` and `scale`
+    pub fn apply<'a>(
+        &self,
+        library: &'a AnimationLibrary,
+        sprite: &mut Sprite<'a>,
+        position: Vector2f,
+        scale: f32,
+    ) {
+        let frame = &library.get(self.animation).frames[self.frame];
+        sprite.set_texture(&library.sheets[frame.sheet], false);
+        sprite.set_texture_rect(frame.rect);
+        sprite.set_position(position);
+        sprite.set_scale(scale);
+    }
+}
+
+// A sprite that plays one animation centred on a point and then goes away,
+// for muzzle flashes and death animations
+pub struct OneShot<'a> {
+    pub sprite: Sprite<'a>,
+    pub animator: Animator,
+    pub position: Vector2f,
+    pub scale: f32,
+}
+
+impl<'a> OneShot<'a> {
+    pub fn new(
+        library: &'a AnimationLibrary,
+        animation: AnimationId,
+        position: Vector2f,
+        scale: f32,
+    ) -> Self {
+        let mut one_shot = OneShot {
+            sprite: Sprite::new(),
+            animator: Animator::new(animation),
+            position,
+            scale,
+        };
+        one_shot.update(library, 0.0);
+        one_shot
+    }
+
+    pub fn update(&mut self, library: &'a AnimationLibrary, dt: f32) {
+        self.animator.update(library, dt);
+        self.animator
+            .apply(library, &mut self.sprite, self.position, self.scale);
+        let rect = self.sprite.texture_rect();
+        self.sprite.set_origin(Vector2f::new(
+            rect.width as f32 / 2.0,
+            rect.height as f32 / 2.0,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // animations without sheets, enough to step through frames
+    fn library(frames: &[f32]) -> AnimationLibrary {
+        let animation = |name: &str, mode| Animation {
+            name: name.to_string(),
+            frames: frames
+                .iter()
+                .map(|&duration| Frame {
+                    sheet: 0,
+                    rect: IntRect::new(0, 0, 1, 1),
+                    duration,
+                })
+                .collect(),
+            mode,
+        };
+        AnimationLibrary {
+            sheet_paths: Vec::new(),
+            sheets: Vec::new(),
+            animations: vec![
+                animation("spin", PlayMode::Loop),
+                animation("burst", PlayMode::Once),
+            ],
+        }
+    }
+
+    #[test]
+    fn cells_are_counted_row_by_row() {
+        let sheet = Vector2u::new(90, 72);
+        assert_eq!(
+            cell_rect(sheet, Some((45, 36)), 0),
+            Some(IntRect::new(0, 0, 45, 36))
+        );
+        assert_eq!(
+            cell_rect(sheet, Some((45, 36)), 3),
+            Some(IntRect::new(45, 36, 45, 36))
+        );
+        assert_eq!(cell_rect(sheet, None, 0), Some(IntRect::new(0, 0, 90, 72)));
+    }
+
+    #[test]
+    fn bad_cells_are_rejected() {
+        let sheet = Vector2u::new(90, 72);
+        assert_eq!(cell_rect(sheet, Some((0, 36)), 0), None);
+        assert_eq!(cell_rect(sheet, Some((45, -1)), 0), None);
+        assert_eq!(cell_rect(sheet, Some((45, 36)), 4), None);
+        assert_eq!(cell_rect(sheet, Some((45, 36)), -1), None);
+        assert_eq!(cell_rect(sheet, Some((100, 36)), 0), None);
+    }
+
+    #[test]
+    fn missing_animations_have_no_id() {
+        let library = library(&[0.1]);
+        assert!(library.id("spin").is_some());
+        assert!(library.id("dance").is_none());
+    }
+
+    #[test]
+    fn looping_animations_wrap_around() {
+        let library = library(&[0.1, 0.2]);
+        let mut animator = Animator::new(library.id("spin").unwrap());
+        animator.update(&library, 0.15);
+        assert_eq!(animator.frame, 1);
+        animator.update(&library, 0.2);
+        assert_eq!(animator.frame, 0);
+        // a long frame skips whole loops
+        animator.update(&library, 0.3 * 10.0);
+        assert_eq!(animator.frame, 0);
+        assert!(!animator.is_finished());
+    }
+
+    #[test]
+    fn once_animations_stop_on_the_last_frame() {
+        let library = library(&[0.1, 0.1, 0.1]);
+        let mut animator = Animator::new(library.id("burst").unwrap());
+        animator.update(&library, 0.25);
+        assert_eq!(animator.frame, 2);
+        assert!(!animator.is_finished());
+        animator.update(&library, 1.0);
+        assert_eq!(animator.frame, 2);
+        assert!(animator.is_finished());
+    }
+
+    #[test]
+    fn playing_the_same_animation_keeps_its_place() {
+        let library = library(&[0.1, 0.1]);
+        let spin = library.id("spin").unwrap();
+        let mut animator = Animator::new(spin);
+        animator.update(&library, 0.15);
+        animator.play(spin);
+        assert_eq!(animator.frame, 1);
+        animator.play(library.id("burst").unwrap());
+        assert_eq!(animator.frame, 0);
+    }
+}
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+
+use sfml::audio::{Music, Sound as Voice, SoundBuffer, SoundSource, SoundStatus};
+use sfml::cpp::FBox;
+use sfml::system::{Vector2f, Vector3f};
+
+use crate::events::{GameEvent, Subscriber};
+use crate::music::{BASS, GAME_STEMS};
+use crate::{HEIGHT, WIDTH};
+
+// Everything gameplay can make a noise about
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Sound {
+    Shot,
+    Hit,
+    Kill,
+    EmptyBattery,
+    WaveClear,
+    GameOver,
+}
+
+impl Sound {
+    const ALL: [Sound; 6] = [
+        Sound::Shot,
+        Sound::Hit,
+        Sound::Kill,
+        Sound::EmptyBattery,
+        Sound::WaveClear,
+        Sound::GameOver,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            Sound::Shot => "assets/sounds/shot.wav",
+            Sound::Hit => "assets/sounds/hit.wav",
+            Sound::Kill => "assets/sounds/kill.wav",
+            Sound::EmptyBattery => "assets/sounds/empty.wav",
+            Sound::WaveClear => "assets/sounds/wave_clear.wav",
+            Sound::GameOver => "assets/sounds/game_over.wav",
+        }
+    }
+
+    // 0..100 like sfml, shots happen all the time so they sit under the rest
+    fn volume(&self) -> f32 {
+        match self {
+            Sound::Shot => 45.0,
+            Sound::Hit => 60.0,
+            Sound::EmptyBattery => 70.0,
+            Sound::Kill | Sound::WaveClear | Sound::GameOver => 80.0,
+        }
+    }
+}
+
+// Looping background music. A track is one or more stems of the same length
+// that play in step; the game track's stems are mixed by music.rs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Track {
+    Menu,
+    Game,
+}
+
+impl Track {
+    fn stems(&self) -> &'static [&'static str] {
+        match self {
+            Track::Menu => &["assets/music/menu.wav"],
+            Track::Game => &[
+                "assets/music/game_bass.wav",
+                "assets/music/game_drums.wav",
+                "assets/music/game_lead.wav",
+                "assets/music/game_alarm.wav",
+            ],
+        }
+    }
+
+    // the mix a track starts with, 0..1 per stem
+    fn initial_levels(&self) -> Vec<f32> {
+        match self {
+            Track::Menu => vec![1.0],
+            Track::Game => {
+                let mut levels = vec![0.0; GAME_STEMS];
+                levels[BASS] = 1.0;
+                levels
+            }
+        }
+    }
+}
+
+const MUSIC_VOLUME: f32 = 40.0;
+// how much quieter a sound is at the very edge of the screen than in the middle
+const EDGE_ATTENUATION: f32 = 0.35;
+
+// Pan (-1 left to 1 right) and volume factor for a sound coming from screen
+// position `x`; mono keeps everything centred at full volume
+fn stereo(x: f32, mono: bool) -> (f32, f32) {
+    if mono {
+        return (0.0, 1.0);
+    }
+    let pan = (x / WIDTH as f32 * 2.0 - 1.0).clamp(-1.0, 1.0);
+    (pan, 1.0 - EDGE_ATTENUATION * pan.abs())
+}
+// seconds for a stem to fade all the way in or out
+const CROSSFADE_TIME: f32 = 1.5;
+
+// Whatever actually makes the noise. The game only talks to `Audio`, which
+// hands the backend one call per sound, so the backend can be swapped for
+// one that does nothing (no audio device) or one that writes things down (tests).
+pub trait AudioBackend {
+    // `pan` goes from -1 (left) to 1 (right)
+    fn play_sound(&mut self, sound: Sound, volume: f32, pan: f32);
+    // starts every stem of `track` silent, None stops the music
+    fn play_music(&mut self, track: Option<Track>);
+    // 0..100 for each stem of the playing track
+    fn set_music_volumes(&mut self, volumes: &[f32]);
+}
+
+// for headless runs and machines without an audio device
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play_sound(&mut self, _sound: Sound, _volume: f32, _pan: f32) {}
+    fn play_music(&mut self, _track: Option<Track>) {}
+    fn set_music_volumes(&mut self, _volumes: &[f32]) {}
+}
+
+// The decoded sound effects, kept apart from the backend because sfml's
+// voices borrow their buffers
+pub struct SoundBank {
+    buffers: HashMap<Sound, FBox<SoundBuffer>>,
+}
+
+impl SoundBank {
+    // None if any sound is missing, the game then runs silent
+    pub fn load() -> Option<Self> {
+        let mut buffers = HashMap::new();
+        for sound in Sound::ALL {
+            match SoundBuffer::from_file(sound.path()) {
+                Ok(buffer) => {
+                    buffers.insert(sound, buffer);
+                }
+                Err(_) => {
+                    eprintln!("Failed to load {}", sound.path());
+                    return None;
+                }
+            }
+        }
+        Some(SoundBank { buffers })
+    }
+}
+
+// enough for a burst of hits on top of shots without cutting anything off
+const VOICES: usize = 16;
+
+pub struct SfmlBackend<'a> {
+    bank: &'a SoundBank,
+    voices: Vec<Voice<'a>>,
+    // the voice to steal when every one is busy, the oldest one
+    next_voice: usize,
+    stems: Vec<Music<'static>>,
+}
+
+impl<'a> SfmlBackend<'a> {
+    pub fn new(bank: &'a SoundBank) -> Self {
+        SfmlBackend {
+            bank,
+            voices: (0..VOICES).map(|_| Voice::new()).collect(),
+            next_voice: 0,
+            stems: Vec::new(),
+        }
+    }
+}
+
+impl AudioBackend for SfmlBackend<'_> {
+    fn play_sound(&mut self, sound: Sound, volume: f32, pan: f32) {
+        let index = self
+            .voices
+            .iter()
+            .position(|v| v.status() == SoundStatus::STOPPED)
+            .unwrap_or(self.next_voice);
+        self.next_voice = (index + 1) % VOICES;
+        let voice = &mut self.voices[index];
+        voice.set_buffer(&self.bank.buffers[&sound]);
+        voice.set_volume(volume);
+        // sfml places mono sounds in 3D, so the voice sits on a unit circle in
+        // front of the listener: straight ahead in the middle, fully to the side at the edges
+        voice.set_relative_to_listener(true);
+        voice.set_position(Vector3f::new(pan, 0.0, -(1.0 - pan * pan).sqrt()));
+        voice.play();
+    }
+
+    fn play_music(&mut self, track: Option<Track>) {
+        for stem in &mut self.stems {
+            stem.stop();
+        }
+        self.stems.clear();
+        let Some(track) = track else {
+            return;
+        };
+        for path in track.stems() {
+            match Music::from_file(path) {
+                Ok(stem) => self.stems.push(stem),
+                Err(_) => {
+                    // a track with a stem missing would play out of step, so none of it plays
+                    eprintln!("Failed to load {}", path);
+                    self.stems.clear();
+                    return;
+                }
+            }
+        }
+        // started together once all are loaded so they stay in step
+        for stem in &mut self.stems {
+            stem.set_looping(true);
+            stem.set_volume(0.0);
+            stem.play();
+        }
+    }
+
+    fn set_music_volumes(&mut self, volumes: &[f32]) {
+        for (stem, volume) in self.stems.iter_mut().zip(volumes) {
+            stem.set_volume(*volume);
+        }
+    }
+}
+
+// Gameplay asks for sounds as things happen, from where they happen on screen;
+// they are played together at the end of the frame, so ten enemies hit by one
+// shot make one hit sound.
+pub struct Audio<'a> {
+    backend: Box<dyn AudioBackend + 'a>,
+    queued: Vec<(Sound, Vector2f)>,
+    pub mono: bool,
+    music: Option<Track>,
+    // where each stem of the playing track is and where it's fading to, 0..1
+    levels: Vec<f32>,
+    targets: Vec<f32>,
+}
+
+impl<'a> Audio<'a> {
+    pub fn new(backend: Box<dyn AudioBackend + 'a>) -> Self {
+        Audio {
+            backend,
+            queued: Vec::new(),
+            mono: false,
+            music: None,
+            levels: Vec::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    // the first position asked for wins when a sound is asked for more than once a frame
+    pub fn play(&mut self, sound: Sound, position: Vector2f) {
+        if !self.queued.iter().any(|(queued, _)| *queued == sound) {
+            self.queued.push((sound, position));
+        }
+    }
+
+    // safe to call every frame, the track only restarts when it changes
+    pub fn set_music(&mut self, track: Option<Track>) {
+        if track != self.music {
+            self.music = track;
+            self.levels = track.map_or(Vec::new(), |t| t.initial_levels());
+            self.targets = self.levels.clone();
+            self.backend.play_music(track);
+            self.push_volumes();
+        }
+    }
+
+    // where the stems should fade to, one level per stem of the playing track;
+    // ignored if it doesn't match, e.g. while the menu music plays
+    pub fn set_stem_levels(&mut self, levels: &[f32]) {
+        if levels.len() == self.targets.len() {
+            self.targets.copy_from_slice(levels);
+        }
+    }
+
+    // fades the stems towards their targets
+    pub fn update(&mut self, dt: f32) {
+        let step = dt / CROSSFADE_TIME;
+        let mut changed = false;
+        for (level, target) in self.levels.iter_mut().zip(&self.targets) {
+            let next = *level + (*target - *level).clamp(-step, step);
+            changed |= next != *level;
+            *level = next;
+        }
+        if changed {
+            self.push_volumes();
+        }
+    }
+
+    fn push_volumes(&mut self) {
+        let volumes: Vec<f32> = self.levels.iter().map(|l| l * MUSIC_VOLUME).collect();
+        self.backend.set_music_volumes(&volumes);
+    }
+
+    pub fn flush(&mut self) {
+        for (sound, position) in self.queued.drain(..) {
+            let (pan, gain) = stereo(position.x, self.mono);
+            self.backend.play_sound(sound, sound.volume() * gain, pan);
+        }
+    }
+}
+
+impl Subscriber for Audio<'_> {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::ShotFired { pos } => self.play(Sound::Shot, pos),
+            GameEvent::BatteryEmpty { pos } => self.play(Sound::EmptyBattery, pos),
+            GameEvent::EnemyHit { pos, .. } => self.play(Sound::Hit, pos),
+            GameEvent::EnemyKilled { pos, .. } => self.play(Sound::Kill, pos),
+            GameEvent::ShotMissed | GameEvent::EnemyBreached { .. } => {}
+            // the stinger comes from the middle, like the banner
+            GameEvent::WaveCleared { .. } => self.play(
+                Sound::WaveClear,
+                Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
+            ),
+            GameEvent::PlayerHit { pos } => self.play(Sound::GameOver, pos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(PartialEq, Debug)]
+    enum Call {
+        Sound(Sound, f32),
+        Music(Option<Track>),
+        Volumes(Vec<f32>),
+    }
+
+    // writes down every call so tests can check what would have been heard
+    #[derive(Clone, Default)]
+    struct RecordingBackend {
+        calls: Rc<RefCell<Vec<Call>>>,
+    }
+
+    impl AudioBackend for RecordingBackend {
+        fn play_sound(&mut self, sound: Sound, _volume: f32, pan: f32) {
+            self.calls.borrow_mut().push(Call::Sound(sound, pan));
+        }
+        fn play_music(&mut self, track: Option<Track>) {
+            self.calls.borrow_mut().push(Call::Music(track));
+        }
+        fn set_music_volumes(&mut self, volumes: &[f32]) {
+            self.calls
+                .borrow_mut()
+                .push(Call::Volumes(volumes.to_vec()));
+        }
+    }
+
+    const CENTRE: Vector2f = Vector2f::new(WIDTH as f32 / 2.0, 300.0);
+
+    fn recorded() -> (Audio<'static>, Rc<RefCell<Vec<Call>>>) {
+        let backend = RecordingBackend::default();
+        let calls = backend.calls.clone();
+        (Audio::new(Box::new(backend)), calls)
+    }
+
+    #[test]
+    fn sounds_wait_for_the_end_of_the_frame() {
+        let (mut audio, calls) = recorded();
+        audio.play(Sound::Shot, CENTRE);
+        assert!(calls.borrow().is_empty());
+        audio.flush();
+        assert_eq!(*calls.borrow(), vec![Call::Sound(Sound::Shot, 0.0)]);
+        audio.flush();
+        assert_eq!(calls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn the_same_sound_plays_once_per_frame() {
+        let (mut audio, calls) = recorded();
+        audio.play(Sound::Hit, CENTRE);
+        audio.play(Sound::Hit, Vector2f::new(0.0, 0.0));
+        audio.play(Sound::Kill, CENTRE);
+        audio.flush();
+        assert_eq!(
+            *calls.borrow(),
+            vec![Call::Sound(Sound::Hit, 0.0), Call::Sound(Sound::Kill, 0.0)]
+        );
+    }
+
+    #[test]
+    fn music_only_changes_when_the_track_does() {
+        let (mut audio, calls) = recorded();
+        audio.set_music(Some(Track::Menu));
+        audio.set_music(Some(Track::Menu));
+        audio.set_music(Some(Track::Game));
+        audio.set_music(None);
+        let tracks: Vec<_> = calls
+            .borrow()
+            .iter()
+            .filter_map(|call| match call {
+                Call::Music(track) => Some(*track),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tracks, vec![Some(Track::Menu), Some(Track::Game), None]);
+    }
+
+    #[test]
+    fn stems_crossfade_instead_of_jumping() {
+        let (mut audio, calls) = recorded();
+        audio.set_music(Some(Track::Game));
+        audio.set_stem_levels(&[1.0, 1.0, 0.0, 0.0]);
+        audio.update(CROSSFADE_TIME / 2.0);
+        let last = |calls: &RefCell<Vec<Call>>| match calls.borrow().last() {
+            Some(Call::Volumes(volumes)) => volumes.clone(),
+            _ => panic!("no volumes set"),
+        };
+        assert_eq!(last(&calls)[1], MUSIC_VOLUME / 2.0);
+        audio.update(CROSSFADE_TIME);
+        assert_eq!(last(&calls), vec![MUSIC_VOLUME, MUSIC_VOLUME, 0.0, 0.0]);
+
+        // nothing left to fade, so the backend isn't bothered
+        let count = calls.borrow().len();
+        audio.update(0.1);
+        assert_eq!(calls.borrow().len(), count);
+    }
+
+    #[test]
+    fn stem_levels_for_another_track_are_ignored() {
+        let (mut audio, _) = recorded();
+        audio.set_music(Some(Track::Menu));
+        audio.set_stem_levels(&[0.0; GAME_STEMS]);
+        assert_eq!(audio.targets, vec![1.0]);
+    }
+
+    #[test]
+    fn null_backend_accepts_everything() {
+        let mut audio = Audio::new(Box::new(NullBackend));
+        audio.set_music(Some(Track::Game));
+        audio.play(Sound::GameOver, CENTRE);
+        audio.flush();
+    }
+
+    #[test]
+    fn sounds_pan_and_fade_towards_the_edges() {
+        assert_eq!(stereo(WIDTH as f32 / 2.0, false), (0.0, 1.0));
+        let (pan, gain) = stereo(0.0, false);
+        assert_eq!(pan, -1.0);
+        assert!(gain < 1.0);
+        assert_eq!(stereo(WIDTH as f32 + 50.0, false).0, 1.0);
+    }
+
+    #[test]
+    fn mono_keeps_everything_centred() {
+        let (mut audio, calls) = recorded();
+        audio.mono = true;
+        audio.play(Sound::Kill, Vector2f::new(10.0, 10.0));
+        audio.flush();
+        assert_eq!(*calls.borrow(), vec![Call::Sound(Sound::Kill, 0.0)]);
+    }
+}
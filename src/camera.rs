@@ -0,0 +1,326 @@
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use sfml::cpp::FBox;
+use sfml::graphics::{FloatRect, RenderTarget, RenderWindow, View};
+use sfml::system::{Vector2f, Vector2i, Vector2u};
+
+use crate::events::{GameEvent, Subscriber};
+use crate::{HEIGHT, WIDTH};
+
+// offset and rotation at full trauma, scaled by trauma squared so small hits stay subtle
+const MAX_SHAKE_OFFSET: f32 = 14.0;
+const MAX_SHAKE_ANGLE: f32 = 2.5;
+// trauma lost per second
+const TRAUMA_DECAY: f32 = 1.6;
+// how quickly a punched zoom eases back to 1.0, higher is snappier
+const ZOOM_SPEED: f32 = 8.0;
+
+// Wraps the world View. Gameplay adds trauma for shake, asks for short
+// hit-stop freezes and punches the zoom; the UI is drawn through a second,
+// fixed view so the HUD never moves. Both views always show the virtual
+// WIDTH x HEIGHT playfield, letterboxed into whatever size the window is.
+pub struct Camera {
+    world_view: FBox<View>,
+    ui_view: FBox<View>,
+    center: Vector2f,
+    size: Vector2f,
+    trauma: f32,
+    hit_stop: f32,
+    zoom: f32,
+    rng: ThreadRng,
+    // from the settings, 0.0 turns shake off and 1.0 is full strength
+    pub shake_scale: f32,
+    pub hit_stop_enabled: bool,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        let center = Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0);
+        let size = Vector2f::new(WIDTH as f32, HEIGHT as f32);
+        Camera {
+            world_view: View::with_center_and_size(center, size),
+            ui_view: View::with_center_and_size(center, size),
+            center,
+            size,
+            trauma: 0.0,
+            hit_stop: 0.0,
+            zoom: 1.0,
+            rng: rand::rng(),
+            shake_scale: 1.0,
+            hit_stop_enabled: true,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    // freezes the simulation for `seconds`, overlapping freezes don't stack
+    pub fn hit_stop(&mut self, seconds: f32) {
+        if self.hit_stop_enabled {
+            self.hit_stop = self.hit_stop.max(seconds);
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.hit_stop > 0.0
+    }
+
+    // jumps the zoom by `amount` (below 1.0 zooms in) and lets it ease back
+    pub fn punch_zoom(&mut self, amount: f32) {
+        self.zoom *= amount;
+    }
+
+    pub fn reset(&mut self) {
+        self.trauma = 0.0;
+        self.hit_stop = 0.0;
+        self.zoom = 1.0;
+    }
+
+    // runs on real time, so it keeps going while the simulation is frozen
+    pub fn update(&mut self, dt: f32) {
+        self.hit_stop = (self.hit_stop - dt).max(0.0);
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.zoom += (1.0 - self.zoom) * (ZOOM_SPEED * dt).min(1.0);
+
+        let shake = self.trauma * self.trauma * self.shake_scale;
+        let offset = Vector2f::new(
+            MAX_SHAKE_OFFSET * shake * self.rng.random_range(-1.0..=1.0),
+            MAX_SHAKE_OFFSET * shake * self.rng.random_range(-1.0..=1.0),
+        );
+        self.world_view.set_center(self.center + offset);
+        self.world_view
+            .set_rotation(MAX_SHAKE_ANGLE * shake * self.rng.random_range(-1.0..=1.0));
+        self.world_view.set_size(self.size * self.zoom);
+    }
+
+    // call whenever the window is created or resized
+    pub fn resize(&mut self, window_size: Vector2u, integer_scaling: bool) {
+        let viewport = letterbox_viewport(window_size, integer_scaling);
+        self.world_view.set_viewport(viewport);
+        self.ui_view.set_viewport(viewport);
+    }
+
+    // window pixel (e.g. from a mouse event) to playfield coordinates
+    pub fn ui_coords(&self, window: &RenderWindow, pixel: Vector2i) -> Vector2f {
+        pixel_to_playfield(pixel, window.size(), self.ui_view.viewport())
+    }
+
+    pub fn apply_world(&self, window: &mut RenderWindow) {
+        window.set_view(&self.world_view);
+    }
+
+    pub fn apply_ui(&self, window: &mut RenderWindow) {
+        window.set_view(&self.ui_view);
+    }
+}
+
+// hits shake the view a little, kills shake it more, freeze it for a moment
+// and punch the zoom in
+impl Subscriber for Camera {
+    fn on_event(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::EnemyHit { .. } => self.add_trauma(0.12),
+            GameEvent::EnemyKilled { .. } => {
+                self.add_trauma(0.25);
+                self.hit_stop(0.05);
+                self.punch_zoom(0.97);
+            }
+            _ => {}
+        }
+    }
+}
+
+// The largest area with the playfield's aspect ratio that fits in the window,
+// centred, as a viewport (fractions of the window). With integer scaling the
+// scale is rounded down to a whole number so pixel art stays crisp, unless the
+// window is smaller than the playfield.
+pub fn letterbox_viewport(window_size: Vector2u, integer_scaling: bool) -> FloatRect {
+    let window_w = window_size.x.max(1) as f32;
+    let window_h = window_size.y.max(1) as f32;
+    let mut scale = (window_w / WIDTH as f32).min(window_h / HEIGHT as f32);
+    if integer_scaling && scale >= 1.0 {
+        scale = scale.floor();
+    }
+
+    let width = WIDTH as f32 * scale / window_w;
+    let height = HEIGHT as f32 * scale / window_h;
+    FloatRect::new((1.0 - width) / 2.0, (1.0 - height) / 2.0, width, height)
+}
+
+// Where a window pixel lands on the playfield when it's drawn into `viewport`,
+// the same as mapping it through the UI view. Pixels in the black bars come
+// out below 0 or past WIDTH x HEIGHT.
+pub fn pixel_to_playfield(pixel: Vector2i, window_size: Vector2u, viewport: FloatRect) -> Vector2f {
+    let window_w = window_size.x.max(1) as f32;
+    let window_h = window_size.y.max(1) as f32;
+    Vector2f::new(
+        (pixel.x as f32 - viewport.left * window_w) / (viewport.width * window_w) * WIDTH as f32,
+        (pixel.y as f32 - viewport.top * window_h) / (viewport.height * window_h) * HEIGHT as f32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(width: u32, height: u32, integer_scaling: bool) -> FloatRect {
+        letterbox_viewport(Vector2u::new(width, height), integer_scaling)
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        let close = actual
+            .iter()
+            .zip(expected)
+            .all(|(a, e)| (a - e).abs() < 1e-4);
+        assert!(close, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn the_playfield_size_fills_the_window() {
+        assert_eq!(
+            viewport(WIDTH, HEIGHT, false),
+            FloatRect::new(0.0, 0.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            viewport(WIDTH, HEIGHT, true),
+            FloatRect::new(0.0, 0.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn wide_windows_get_bars_left_and_right() {
+        assert_eq!(
+            viewport(WIDTH * 2, HEIGHT, false),
+            FloatRect::new(0.25, 0.0, 0.5, 1.0)
+        );
+    }
+
+    #[test]
+    fn tall_windows_get_bars_top_and_bottom() {
+        assert_eq!(
+            viewport(WIDTH, HEIGHT * 2, false),
+            FloatRect::new(0.0, 0.25, 1.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn integer_scaling_rounds_down_above_1x() {
+        // 2.5x fits, 2x is used
+        assert_eq!(
+            viewport(WIDTH * 5 / 2, HEIGHT * 5 / 2, false),
+            FloatRect::new(0.0, 0.0, 1.0, 1.0)
+        );
+        let rounded = viewport(WIDTH * 5 / 2, HEIGHT * 5 / 2, true);
+        assert_close(
+            &[rounded.left, rounded.top, rounded.width, rounded.height],
+            &[0.1, 0.1, 0.8, 0.8],
+        );
+    }
+
+    #[test]
+    fn integer_scaling_leaves_small_windows_alone() {
+        assert_eq!(
+            viewport(WIDTH / 2, HEIGHT / 2, true),
+            viewport(WIDTH / 2, HEIGHT / 2, false)
+        );
+        assert_eq!(
+            viewport(WIDTH / 2, HEIGHT / 2, true),
+            FloatRect::new(0.0, 0.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn a_minimised_window_still_gets_a_usable_viewport() {
+        let zero = viewport(0, 0, true);
+        for value in [zero.left, zero.top, zero.width, zero.height] {
+            assert!(value.is_finite());
+        }
+        let pixel = pixel_to_playfield(Vector2i::new(0, 0), Vector2u::new(0, 0), zero);
+        assert!(pixel.x.is_finite() && pixel.y.is_finite());
+    }
+
+    #[test]
+    fn window_pixels_map_onto_the_playfield() {
+        let window = Vector2u::new(WIDTH * 2, HEIGHT);
+        let viewport = letterbox_viewport(window, false);
+        let to_playfield = |x, y| pixel_to_playfield(Vector2i::new(x, y), window, viewport);
+        assert_eq!(to_playfield(400, 0), Vector2f::new(0.0, 0.0));
+        assert_eq!(to_playfield(800, 300), Vector2f::new(400.0, 300.0));
+        assert_eq!(to_playfield(1200, 600), Vector2f::new(800.0, 600.0));
+        // in the black bar
+        assert!(to_playfield(100, 300).x < 0.0);
+    }
+
+    #[test]
+    fn the_mapping_follows_integer_scaling() {
+        let window = Vector2u::new(WIDTH * 5 / 2, HEIGHT * 5 / 2);
+        let viewport = letterbox_viewport(window, true);
+        let top_left = pixel_to_playfield(Vector2i::new(200, 150), window, viewport);
+        assert_close(&[top_left.x, top_left.y], &[0.0, 0.0]);
+        let middle = pixel_to_playfield(Vector2i::new(1000, 750), window, viewport);
+        assert_close(&[middle.x, middle.y], &[400.0, 300.0]);
+    }
+
+    fn killed() -> GameEvent {
+        GameEvent::EnemyKilled {
+            enemy_type: crate::enemy::EnemyType::Default,
+            pos: Vector2f::new(0.0, 0.0),
+            angle: 0.0,
+            points: 100,
+            chain: 1,
+        }
+    }
+
+    #[test]
+    fn trauma_is_capped_and_wears_off() {
+        let mut camera = Camera::new();
+        camera.add_trauma(0.7);
+        camera.add_trauma(0.7);
+        assert_eq!(camera.trauma, 1.0);
+        camera.update(0.5);
+        assert_close(&[camera.trauma], &[1.0 - TRAUMA_DECAY * 0.5]);
+        camera.update(1.0);
+        assert_eq!(camera.trauma, 0.0);
+    }
+
+    #[test]
+    fn hit_stop_lasts_as_long_as_the_longest_freeze() {
+        let mut camera = Camera::new();
+        camera.hit_stop(0.05);
+        camera.hit_stop(0.02);
+        camera.update(0.04);
+        assert!(camera.is_frozen());
+        camera.update(0.02);
+        assert!(!camera.is_frozen());
+
+        camera.hit_stop_enabled = false;
+        camera.hit_stop(0.05);
+        assert!(!camera.is_frozen());
+    }
+
+    #[test]
+    fn a_zoom_punch_eases_back() {
+        let mut camera = Camera::new();
+        camera.punch_zoom(0.9);
+        assert_close(&[camera.zoom], &[0.9]);
+        camera.update(0.05);
+        assert!(camera.zoom > 0.9 && camera.zoom < 1.0);
+        for _ in 0..60 {
+            camera.update(1.0 / 60.0);
+        }
+        assert_close(&[camera.zoom], &[1.0]);
+    }
+
+    #[test]
+    fn a_kill_shakes_freezes_and_zooms() {
+        let mut camera = Camera::new();
+        camera.on_event(&killed());
+        assert!(camera.trauma > 0.0);
+        assert!(camera.is_frozen());
+        assert!(camera.zoom < 1.0);
+        camera.reset();
+        assert_eq!((camera.trauma, camera.zoom), (0.0, 1.0));
+        assert!(!camera.is_frozen());
+    }
+}
@@ -0,0 +1,125 @@
+// seconds the player has to land the next kill before the multiplier drops a step
+pub const COMBO_WINDOW: f32 = 2.5;
+pub const MAX_MULTIPLIER: i32 = 8;
+// chained kills needed to go up one multiplier step
+const KILLS_PER_STEP: i32 = 3;
+// extra points for every enemy after the first killed by the same projectile
+const MULTI_KILL_BONUS: i32 = 250;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Combo {
+    pub chain: i32,
+    pub multiplier: i32,
+    pub timer: f32,
+}
+
+impl Combo {
+    pub fn new() -> Self {
+        Combo {
+            chain: 0,
+            multiplier: 1,
+            timer: 0.0,
+        }
+    }
+
+    // registers a kill and returns the points it is worth after bonuses
+    pub fn register_kill(&mut self, points: i32, kills_by_projectile: i32) -> i32 {
+        self.chain += 1;
+        self.multiplier = (1 + self.chain / KILLS_PER_STEP).min(MAX_MULTIPLIER);
+        self.timer = COMBO_WINDOW;
+
+        let bonus = MULTI_KILL_BONUS * (kills_by_projectile - 1).max(0);
+        (points + bonus) * self.multiplier
+    }
+
+    // when the window runs out the multiplier loses one step and the window restarts,
+    // so a long chain bleeds off gradually instead of vanishing at once
+    pub fn update(&mut self, dt: f32) {
+        if self.timer <= 0.0 {
+            return;
+        }
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            if self.multiplier > 1 {
+                self.multiplier -= 1;
+                self.chain = (self.multiplier - 1) * KILLS_PER_STEP;
+                self.timer = COMBO_WINDOW;
+            } else {
+                self.chain = 0;
+                self.timer = 0.0;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Combo::new();
+    }
+
+    // how much of the combo window is left, from 0.0 to 1.0
+    pub fn timer_fraction(&self) -> f32 {
+        (self.timer / COMBO_WINDOW).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_three_kills_is_a_step_up_to_the_cap() {
+        let mut combo = Combo::new();
+        let multipliers: Vec<i32> = (0..6)
+            .map(|_| {
+                combo.register_kill(100, 1);
+                combo.multiplier
+            })
+            .collect();
+        assert_eq!(multipliers, [1, 1, 2, 2, 2, 3]);
+
+        for _ in 0..30 {
+            combo.register_kill(100, 1);
+        }
+        assert_eq!(combo.multiplier, MAX_MULTIPLIER);
+        assert_eq!(combo.register_kill(100, 1), 100 * MAX_MULTIPLIER);
+    }
+
+    #[test]
+    fn multi_kills_earn_a_bonus() {
+        let mut combo = Combo::new();
+        assert_eq!(combo.register_kill(100, 3), 100 + 2 * MULTI_KILL_BONUS);
+    }
+
+    #[test]
+    fn the_multiplier_bleeds_off_a_step_per_window() {
+        let mut combo = Combo::new();
+        for _ in 0..6 {
+            combo.register_kill(100, 1);
+        }
+        assert_eq!(combo.multiplier, 3);
+
+        combo.update(COMBO_WINDOW - 0.1);
+        assert_eq!(combo.multiplier, 3);
+        combo.update(0.2);
+        assert_eq!(combo.multiplier, 2);
+        assert_eq!(combo.chain, KILLS_PER_STEP);
+        assert_eq!(combo.timer, COMBO_WINDOW);
+
+        combo.update(COMBO_WINDOW);
+        combo.update(COMBO_WINDOW);
+        assert_eq!(combo.multiplier, 1);
+        assert_eq!(combo.chain, 0);
+        assert_eq!(combo.timer_fraction(), 0.0);
+    }
+
+    #[test]
+    fn reset_starts_over() {
+        let mut combo = Combo::new();
+        for _ in 0..9 {
+            combo.register_kill(100, 1);
+        }
+        combo.reset();
+        assert_eq!(combo.multiplier, 1);
+        assert_eq!(combo.chain, 0);
+        assert_eq!(combo.timer, 0.0);
+    }
+}
@@ -0,0 +1,237 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Difficulty {
+    pub fn all() -> &'static [Difficulty] {
+        use Difficulty::*;
+        static ALL: [Difficulty; 4] = [Easy, Normal, Hard, Custom];
+        &ALL
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Custom => "custom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::all().iter().copied().find(|d| d.name() == name)
+    }
+
+    // Custom uses whatever the player put in their settings
+    pub fn params(&self, custom: &DifficultyParams) -> DifficultyParams {
+        match *self {
+            Difficulty::Easy => DifficultyParams {
+                base_enemy_count: 2,
+                fast_chance: 0.1,
+                fast_chance_per_wave: 0.05,
+                armored_chance: 0.05,
+                armored_chance_per_wave: 0.03,
+                speed_scale: 0.75,
+                health_scale: 0.75,
+                reload_interval: 0.7,
+                battery_size: 7,
+            },
+            Difficulty::Normal => DifficultyParams::NORMAL,
+            Difficulty::Hard => DifficultyParams {
+                base_enemy_count: 4,
+                fast_chance: 0.3,
+                fast_chance_per_wave: 0.12,
+                armored_chance: 0.15,
+                armored_chance_per_wave: 0.07,
+                speed_scale: 1.3,
+                health_scale: 1.25,
+                reload_interval: 1.3,
+                battery_size: 4,
+            },
+            Difficulty::Custom => *custom,
+        }
+    }
+}
+
+// Everything that makes a run harder or easier. Enemy speed and health are
+// multipliers on EnemyType::default_speed and EnemyType::default_health.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DifficultyParams {
+    pub base_enemy_count: usize,
+    pub fast_chance: f64,
+    pub fast_chance_per_wave: f64,
+    pub armored_chance: f64,
+    pub armored_chance_per_wave: f64,
+    pub speed_scale: f32,
+    pub health_scale: f32,
+    pub reload_interval: f32,
+    pub battery_size: i32,
+}
+
+impl DifficultyParams {
+    pub const NORMAL: DifficultyParams = DifficultyParams {
+        base_enemy_count: 3,
+        fast_chance: 0.2,
+        fast_chance_per_wave: 0.1,
+        armored_chance: 0.1,
+        armored_chance_per_wave: 0.05,
+        speed_scale: 1.0,
+        health_scale: 1.0,
+        reload_interval: 1.0,
+        battery_size: 5,
+    };
+
+    pub fn fast_chance_at(&self, wave_number: u32) -> f64 {
+        (self.fast_chance + self.fast_chance_per_wave * wave_number as f64).clamp(0.0, 1.0)
+    }
+
+    pub fn armored_chance_at(&self, wave_number: u32) -> f64 {
+        (self.armored_chance + self.armored_chance_per_wave * wave_number as f64).clamp(0.0, 1.0)
+    }
+
+    // key/value pairs used by the settings and high score files
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("base_enemy_count", self.base_enemy_count.to_string()),
+            ("fast_chance", self.fast_chance.to_string()),
+            (
+                "fast_chance_per_wave",
+                self.fast_chance_per_wave.to_string(),
+            ),
+            ("armored_chance", self.armored_chance.to_string()),
+            (
+                "armored_chance_per_wave",
+                self.armored_chance_per_wave.to_string(),
+            ),
+            ("speed_scale", self.speed_scale.to_string()),
+            ("health_scale", self.health_scale.to_string()),
+            ("reload_interval", self.reload_interval.to_string()),
+            ("battery_size", self.battery_size.to_string()),
+        ]
+    }
+
+    // unknown keys and unparsable values are ignored so old files keep loading
+    pub fn set_field(&mut self, key: &str, value: &str) {
+        match key {
+            "base_enemy_count" => parse_into(value, &mut self.base_enemy_count),
+            "fast_chance" => parse_into(value, &mut self.fast_chance),
+            "fast_chance_per_wave" => parse_into(value, &mut self.fast_chance_per_wave),
+            "armored_chance" => parse_into(value, &mut self.armored_chance),
+            "armored_chance_per_wave" => parse_into(value, &mut self.armored_chance_per_wave),
+            "speed_scale" => parse_into(value, &mut self.speed_scale),
+            "health_scale" => parse_into(value, &mut self.health_scale),
+            "reload_interval" => parse_into(value, &mut self.reload_interval),
+            "battery_size" => parse_into(value, &mut self.battery_size),
+            _ => {}
+        }
+        self.sanitize();
+    }
+
+    // keeps hand-edited custom values inside what the game can handle; "NaN"
+    // and "inf" parse as floats, so those fall back to Normal's value
+    fn sanitize(&mut self) {
+        let normal = DifficultyParams::NORMAL;
+        self.fast_chance = finite_or(self.fast_chance, normal.fast_chance).clamp(0.0, 1.0);
+        self.fast_chance_per_wave =
+            finite_or(self.fast_chance_per_wave, normal.fast_chance_per_wave).clamp(-1.0, 1.0);
+        self.armored_chance = finite_or(self.armored_chance, normal.armored_chance).clamp(0.0, 1.0);
+        self.armored_chance_per_wave =
+            finite_or(self.armored_chance_per_wave, normal.armored_chance_per_wave)
+                .clamp(-1.0, 1.0);
+        self.speed_scale = finite_or(self.speed_scale, normal.speed_scale);
+        self.health_scale = finite_or(self.health_scale, normal.health_scale);
+        self.reload_interval = finite_or(self.reload_interval, normal.reload_interval);
+
+        self.base_enemy_count = self.base_enemy_count.max(1);
+        self.speed_scale = self.speed_scale.clamp(0.1, 5.0);
+        self.health_scale = self.health_scale.clamp(0.1, 10.0);
+        self.reload_interval = self.reload_interval.clamp(0.05, 10.0);
+        self.battery_size = self.battery_size.clamp(1, 20);
+    }
+}
+
+fn finite_or<T: Into<f64> + Copy>(value: T, default: T) -> T {
+    if value.into().is_finite() {
+        value
+    } else {
+        default
+    }
+}
+
+fn parse_into<T: std::str::FromStr>(value: &str, target: &mut T) {
+    if let Ok(parsed) = value.trim().parse() {
+        *target = parsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_presets_scale_everything_up() {
+        let custom = DifficultyParams::NORMAL;
+        let easy = Difficulty::Easy.params(&custom);
+        let normal = Difficulty::Normal.params(&custom);
+        let hard = Difficulty::Hard.params(&custom);
+        for (lower, higher) in [(easy, normal), (normal, hard)] {
+            assert!(lower.base_enemy_count < higher.base_enemy_count);
+            assert!(lower.speed_scale < higher.speed_scale);
+            assert!(lower.health_scale < higher.health_scale);
+            assert!(lower.reload_interval < higher.reload_interval);
+            assert!(lower.battery_size > higher.battery_size);
+            assert!(lower.fast_chance_at(3) < higher.fast_chance_at(3));
+            assert!(lower.armored_chance_at(3) < higher.armored_chance_at(3));
+        }
+    }
+
+    #[test]
+    fn custom_uses_the_players_params() {
+        let mut custom = DifficultyParams::NORMAL;
+        custom.battery_size = 12;
+        assert_eq!(Difficulty::Custom.params(&custom), custom);
+    }
+
+    #[test]
+    fn chances_stay_probabilities_as_waves_go_on() {
+        let params = Difficulty::Hard.params(&DifficultyParams::NORMAL);
+        assert_eq!(params.fast_chance_at(1000), 1.0);
+        let mut shrinking = DifficultyParams::NORMAL;
+        shrinking.set_field("armored_chance_per_wave", "-0.5");
+        assert_eq!(shrinking.armored_chance_at(10), 0.0);
+    }
+
+    #[test]
+    fn non_finite_values_fall_back_to_normal() {
+        let mut params = DifficultyParams::NORMAL;
+        for key in ["fast_chance", "armored_chance_per_wave", "speed_scale"] {
+            params.set_field(key, "NaN");
+        }
+        params.set_field("health_scale", "inf");
+        params.set_field("reload_interval", "-inf");
+        assert_eq!(params, DifficultyParams::NORMAL);
+        assert!(params.fast_chance_at(5).is_finite());
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let mut params = DifficultyParams::NORMAL;
+        params.set_field("base_enemy_count", "0");
+        params.set_field("fast_chance", "7");
+        params.set_field("speed_scale", "-3");
+        params.set_field("battery_size", "500");
+        params.set_field("reload_interval", "not a number");
+        assert_eq!(params.base_enemy_count, 1);
+        assert_eq!(params.fast_chance, 1.0);
+        assert_eq!(params.speed_scale, 0.1);
+        assert_eq!(params.battery_size, 20);
+        assert_eq!(
+            params.reload_interval,
+            DifficultyParams::NORMAL.reload_interval
+        );
+    }
+}
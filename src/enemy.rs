@@ -0,0 +1,449 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sfml::graphics::{
+    Color, RectangleShape, RenderTarget, RenderWindow, Shape, Sprite, Transformable,
+};
+use sfml::system::Vector2f;
+use std::collections::HashMap;
+
+use crate::WIDTH;
+use crate::animation::{AnimationId, AnimationLibrary, Animator};
+use crate::difficulty::DifficultyParams;
+use crate::iso;
+
+// how long the red tint stays on after a hit, in seconds
+const HIT_FLASH_TIME: f32 = 0.1;
+const HIT_FLASH_COLOR: Color = Color::rgb(255, 90, 90);
+// how far a hit pushes an enemy along the projectile's path, in world units
+const KNOCKBACK_DISTANCE: f32 = 20.0;
+// share of the push still to come after each 1/60 of a second, so most of it
+// happens in the first few frames whatever the frame rate
+const KNOCKBACK_DAMPING: f32 = 0.8;
+const HEALTH_BAR_HEIGHT: f32 = 5.0;
+
+// cracks as (x, y, length, angle), laid out on a 64x64 enemy and stretched to
+// the size the sprite is drawn at; each damage stage adds one more
+const CRACK_LAYOUT_SIZE: f32 = 64.0;
+const CRACKS: [(f32, f32, f32, f32); 3] = [
+    (20.0, 18.0, 22.0, 35.0),
+    (44.0, 24.0, 18.0, 120.0),
+    (26.0, 44.0, 20.0, -20.0),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EnemyType {
+    Default,
+    Armored,
+    Fast,
+}
+
+impl EnemyType {
+    pub const ALL: [EnemyType; 3] = [EnemyType::Default, EnemyType::Fast, EnemyType::Armored];
+
+    // used in save files
+    pub fn name(&self) -> &'static str {
+        match *self {
+            EnemyType::Default => "default",
+            EnemyType::Armored => "armored",
+            EnemyType::Fast => "fast",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<EnemyType> {
+        EnemyType::ALL.into_iter().find(|t| t.name() == name)
+    }
+    // world units per 1/60 of a second, see movement
+    pub fn default_speed(&self) -> f32 {
+        match *self {
+            EnemyType::Default => 1.0,
+            EnemyType::Armored => 0.5,
+            EnemyType::Fast => 2.0,
+        }
+    }
+    pub fn default_health(&self) -> i32 {
+        match *self {
+            EnemyType::Default => 2,
+            EnemyType::Armored => 4,
+            EnemyType::Fast => 1,
+        }
+    }
+    pub fn points_gained(&self) -> i32 {
+        match *self {
+            EnemyType::Default => 200,
+            EnemyType::Armored => 400,
+            EnemyType::Fast => 150,
+        }
+    }
+    // names in assets/animations.txt
+    pub fn idle_animation(&self) -> &'static str {
+        match *self {
+            EnemyType::Default => "enemy_default_idle",
+            EnemyType::Armored => "enemy_armored_idle",
+            EnemyType::Fast => "enemy_fast_idle",
+        }
+    }
+    pub fn death_animation(&self) -> &'static str {
+        match *self {
+            EnemyType::Default => "enemy_default_death",
+            EnemyType::Armored => "enemy_armored_death",
+            EnemyType::Fast => "enemy_fast_death",
+        }
+    }
+}
+
+// Every enemy type's animations, looked up in the library once at load
+pub struct EnemyAnimations {
+    idle: HashMap<EnemyType, AnimationId>,
+    death: HashMap<EnemyType, AnimationId>,
+}
+
+impl EnemyAnimations {
+    // an enemy can't be drawn without its idle animation; a missing death
+    // animation only means it vanishes without one
+    pub fn load(library: &AnimationLibrary) -> Self {
+        let mut animations = EnemyAnimations {
+            idle: HashMap::new(),
+            death: HashMap::new(),
+        };
+        for enemy_type in EnemyType::ALL {
+            let idle = enemy_type.idle_animation();
+            let id = library
+                .id(idle)
+                .unwrap_or_else(|| panic!("Missing animation {}", idle));
+            animations.idle.insert(enemy_type, id);
+
+            match library.id(enemy_type.death_animation()) {
+                Some(id) => {
+                    animations.death.insert(enemy_type, id);
+                }
+                None => eprintln!("Missing animation {}", enemy_type.death_animation()),
+            }
+        }
+        animations
+    }
+
+    pub fn idle(&self, enemy_type: EnemyType) -> AnimationId {
+        self.idle[&enemy_type]
+    }
+
+    pub fn death(&self, enemy_type: EnemyType) -> Option<AnimationId> {
+        self.death.get(&enemy_type).copied()
+    }
+}
+
+// ENEMY AND WAVES
+pub struct Enemy<'a> {
+    pub sprite: Sprite<'a>,
+    // world position, the sprite is moved to its projection every update
+    pub world: Vector2f,
+    pub enemy_type: EnemyType,
+    pub alive: bool,
+    pub speed: f32,
+    pub health: i32,
+    // default_health scaled by the difficulty, what `health` is compared against
+    pub max_health: i32,
+    pub flash_timer: f32,
+    // the part of the push from hits the enemy hasn't moved yet
+    pub knockback: Vector2f,
+    pub animator: Animator,
+}
+
+impl<'a> Enemy<'a> {
+    // `direction` is the projectile's heading in world space
+    pub fn hit(&mut self, direction: Vector2f) {
+        self.health -= 1;
+        self.flash_timer = HIT_FLASH_TIME;
+        self.knockback += direction * KNOCKBACK_DISTANCE;
+    }
+
+    // enemies fly straight down the world y axis towards the player's side
+    pub fn update(&mut self, library: &'a AnimationLibrary, dt: f32) {
+        let pushed = knockback_step(self.knockback, dt);
+        self.world += movement(self.speed, dt) + pushed;
+        self.knockback -= pushed;
+
+        self.flash_timer = (self.flash_timer - dt).max(0.0);
+        self.sprite.set_color(self.tint());
+        self.animator.update(library, dt);
+        self.animator.apply(
+            library,
+            &mut self.sprite,
+            iso::world_to_screen(self.world),
+            2.0,
+        );
+    }
+
+    // the middle of the current frame as drawn, the sheets' cells aren't square
+    pub fn center(&self) -> Vector2f {
+        let bounds = self.sprite.global_bounds();
+        Vector2f::new(
+            bounds.left + bounds.width / 2.0,
+            bounds.top + bounds.height / 2.0,
+        )
+    }
+
+    pub fn health_fraction(&self) -> f32 {
+        health_fraction(self.health, self.max_health)
+    }
+
+    pub fn damage_stage(&self) -> usize {
+        damage_stage(self.health, self.max_health)
+    }
+
+    // red while flashing, otherwise slightly darker the more damage it has taken
+    fn tint(&self) -> Color {
+        if self.flash_timer > 0.0 {
+            return HIT_FLASH_COLOR;
+        }
+        let shade = (155.0 + 100.0 * self.health_fraction()) as u8;
+        Color::rgb(255, shade, shade)
+    }
+}
+
+fn health_fraction(health: i32, max_health: i32) -> f32 {
+    (health as f32 / max_health as f32).clamp(0.0, 1.0)
+}
+
+// 0 when untouched, up to CRACKS.len() just before dying
+fn damage_stage(health: i32, max_health: i32) -> usize {
+    if health >= max_health {
+        return 0;
+    }
+    let stage = ((1.0 - health_fraction(health, max_health)) * CRACKS.len() as f32).ceil() as usize;
+    stage.clamp(1, CRACKS.len())
+}
+
+// how far an enemy flies on its own in `dt` seconds, on frame time like the
+// knockback so a hit pushes it back the same share of its path at any frame rate
+fn movement(speed: f32, dt: f32) -> Vector2f {
+    Vector2f::new(0.0, -speed * dt * 60.0)
+}
+
+// how much of the remaining knockback is moved in `dt` seconds
+fn knockback_step(remaining: Vector2f, dt: f32) -> Vector2f {
+    remaining * (1.0 - KNOCKBACK_DAMPING.powf(dt * 60.0))
+}
+
+// Draws cracks and the optional health bar on top of damaged enemies
+pub struct DamageOverlay<'a> {
+    shape: RectangleShape<'a>,
+}
+
+impl DamageOverlay<'_> {
+    pub fn new() -> Self {
+        DamageOverlay {
+            shape: RectangleShape::new(),
+        }
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow, enemy: &Enemy, show_health_bar: bool) {
+        let bounds = enemy.sprite.global_bounds();
+        let pos = Vector2f::new(bounds.left, bounds.top);
+        let stretch = Vector2f::new(bounds.width, bounds.height) / CRACK_LAYOUT_SIZE;
+
+        self.shape.set_fill_color(Color::rgba(20, 10, 10, 200));
+        self.shape.set_origin(Vector2f::new(0.0, 1.0));
+        for &(x, y, length, angle) in CRACKS.iter().take(enemy.damage_stage()) {
+            self.shape.set_size(Vector2f::new(length * stretch.x, 2.0));
+            self.shape.set_rotation(angle);
+            self.shape
+                .set_position(pos + Vector2f::new(x * stretch.x, y * stretch.y));
+            window.draw(&self.shape);
+        }
+        self.shape.set_rotation(0.0);
+        self.shape.set_origin(Vector2f::new(0.0, 0.0));
+
+        // single hit enemies have nothing to show, full health ones are left uncluttered
+        if !show_health_bar || enemy.max_health <= 1 || enemy.health >= enemy.max_health {
+            return;
+        }
+        let bar_pos = pos + Vector2f::new(0.0, -HEALTH_BAR_HEIGHT - 3.0);
+        self.shape.set_position(bar_pos);
+        self.shape
+            .set_size(Vector2f::new(bounds.width, HEALTH_BAR_HEIGHT));
+        self.shape.set_fill_color(Color::rgba(0, 0, 0, 160));
+        window.draw(&self.shape);
+
+        let fraction = enemy.health_fraction();
+        self.shape
+            .set_size(Vector2f::new(bounds.width * fraction, HEALTH_BAR_HEIGHT));
+        self.shape.set_fill_color(if fraction > 0.5 {
+            Color::rgb(90, 200, 90)
+        } else if fraction > 0.25 {
+            Color::rgb(230, 200, 60)
+        } else {
+            Color::rgb(220, 70, 60)
+        });
+        window.draw(&self.shape);
+    }
+}
+
+#[derive(Clone)]
+pub enum EnemyPositions {
+    A1,
+    B1,
+    C1,
+    D1,
+    A2,
+    B2,
+    C2,
+    A3,
+    B3,
+    A4,
+}
+
+impl EnemyPositions {
+    pub fn return_in_order() -> &'static [EnemyPositions] {
+        use EnemyPositions::*;
+        static POSITIONS: [EnemyPositions; 10] = [A1, B1, C1, D1, A2, B2, C2, A3, B3, A4];
+        &POSITIONS
+    }
+    // formation slots are laid out on screen and turned into world positions at spawn
+    pub fn value(&self) -> Vector2f {
+        match *self {
+            EnemyPositions::A1 => Vector2f::new(WIDTH as f32 - 65.0, 1.0),
+            EnemyPositions::B1 => Vector2f::new(WIDTH as f32 - 135.0, 1.0),
+            EnemyPositions::C1 => Vector2f::new(WIDTH as f32 - 205.0, 1.0),
+            EnemyPositions::D1 => Vector2f::new(WIDTH as f32 - 275.0, 1.0),
+            EnemyPositions::A2 => Vector2f::new(WIDTH as f32 - 65.0, 71.0),
+            EnemyPositions::B2 => Vector2f::new(WIDTH as f32 - 135.0, 71.0),
+            EnemyPositions::C2 => Vector2f::new(WIDTH as f32 - 205.0, 71.0),
+            EnemyPositions::A3 => Vector2f::new(WIDTH as f32 - 65.0, 141.0),
+            EnemyPositions::B3 => Vector2f::new(WIDTH as f32 - 135.0, 141.0),
+            EnemyPositions::A4 => Vector2f::new(WIDTH as f32 - 65.0, 211.0),
+        }
+    }
+}
+
+pub struct Wave<'a> {
+    pub enemies: Vec<Enemy<'a>>,
+}
+
+// A full-health enemy of `enemy_type` at `world`, with the difficulty's speed and health
+pub fn spawn_enemy<'a>(
+    animations: &'a AnimationLibrary,
+    enemy_animations: &EnemyAnimations,
+    enemy_type: EnemyType,
+    world: Vector2f,
+    params: &DifficultyParams,
+) -> Enemy<'a> {
+    let animator = Animator::new(enemy_animations.idle(enemy_type));
+    let mut sprite = Sprite::new();
+    animator.apply(animations, &mut sprite, iso::world_to_screen(world), 2.0);
+
+    let health = ((enemy_type.default_health() as f32 * params.health_scale).round() as i32).max(1);
+    Enemy {
+        sprite,
+        speed: enemy_type.default_speed() * params.speed_scale,
+        health,
+        max_health: health,
+        enemy_type,
+        alive: true,
+        world,
+        flash_timer: 0.0,
+        knockback: Vector2f::new(0.0, 0.0),
+        animator,
+    }
+}
+
+// Each wave's enemy mix comes from the run's seed and the wave number alone, so
+// a resumed run carries on with the waves it would have had
+pub fn spawn_wave<'a>(
+    animations: &'a AnimationLibrary,
+    enemy_animations: &EnemyAnimations,
+    wave_number: u32,
+    params: &DifficultyParams,
+    seed: u64,
+) -> Wave<'a> {
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(wave_number as u64));
+    let positions = EnemyPositions::return_in_order();
+    let mut enemies = Vec::new();
+
+    // Spawn more enemies as wave_number increases
+    let enemy_count = (params.base_enemy_count + wave_number as usize).min(positions.len());
+
+    for position in positions.iter().take(enemy_count) {
+        // Randomly pick a variant — more fast/tank as wave number increases
+        let variant = if rng.random_bool(params.fast_chance_at(wave_number)) {
+            EnemyType::Fast
+        } else if rng.random_bool(params.armored_chance_at(wave_number)) {
+            EnemyType::Armored
+        } else {
+            EnemyType::Default
+        };
+
+        let world = iso::screen_to_world(position.value());
+        enemies.push(spawn_enemy(
+            animations,
+            enemy_animations,
+            variant,
+            world,
+            params,
+        ));
+    }
+
+    Wave { enemies }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_fraction_stays_between_0_and_1() {
+        assert_eq!(health_fraction(4, 4), 1.0);
+        assert_eq!(health_fraction(1, 4), 0.25);
+        assert_eq!(health_fraction(-2, 4), 0.0);
+    }
+
+    #[test]
+    fn every_hit_adds_a_crack_until_they_run_out() {
+        assert_eq!(damage_stage(4, 4), 0);
+        let stages: Vec<usize> = (1..4).rev().map(|health| damage_stage(health, 4)).collect();
+        assert_eq!(stages, [1, 2, 3]);
+        assert_eq!(damage_stage(0, 4), CRACKS.len());
+        // one hit from a lot of health still shows
+        assert_eq!(damage_stage(99, 100), 1);
+        assert_eq!(damage_stage(1, 100), CRACKS.len());
+    }
+
+    #[test]
+    fn knockback_covers_the_same_distance_at_any_frame_rate() {
+        let push = Vector2f::new(KNOCKBACK_DISTANCE, 0.0);
+        let travelled = |fps: u32| {
+            let mut remaining = push;
+            let mut moved = Vector2f::new(0.0, 0.0);
+            for _ in 0..fps {
+                let step = knockback_step(remaining, 1.0 / fps as f32);
+                moved += step;
+                remaining -= step;
+            }
+            moved.x
+        };
+        let at_60 = travelled(60);
+        assert!(at_60 > KNOCKBACK_DISTANCE * 0.99);
+        assert!((travelled(30) - at_60).abs() < 0.01);
+        assert!((travelled(144) - at_60).abs() < 0.01);
+        assert_eq!(knockback_step(push, 0.0), Vector2f::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn flying_and_knockback_add_up_the_same_at_any_frame_rate() {
+        let speed = EnemyType::Default.default_speed();
+        // hit head on, so the push works against the flight
+        let travelled = |fps: u32| {
+            let dt = 1.0 / fps as f32;
+            let mut knockback = Vector2f::new(0.0, KNOCKBACK_DISTANCE);
+            let mut world = Vector2f::new(0.0, 0.0);
+            for _ in 0..fps / 2 {
+                let pushed = knockback_step(knockback, dt);
+                world += movement(speed, dt) + pushed;
+                knockback -= pushed;
+            }
+            world.y
+        };
+        let at_60 = travelled(60);
+        assert_eq!(movement(speed, 1.0 / 60.0), Vector2f::new(0.0, -speed));
+        assert!((travelled(30) - at_60).abs() < 0.01);
+        assert!((travelled(144) - at_60).abs() < 0.01);
+    }
+}
@@ -0,0 +1,137 @@
+use sfml::system::Vector2f;
+
+use crate::enemy::EnemyType;
+
+// Things that happen during a run. The game loop publishes them and whatever
+// cares (sound, particles, the camera, the log) reacts in its own module.
+// Positions are on screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    ShotFired {
+        pos: Vector2f,
+    },
+    // a shot left the screen without hitting anything
+    ShotMissed,
+    // fire was pressed with nothing left in the battery
+    BatteryEmpty {
+        pos: Vector2f,
+    },
+    // `angle` is the direction the shot was travelling, in degrees
+    EnemyHit {
+        enemy_type: EnemyType,
+        pos: Vector2f,
+        angle: f32,
+        // false when the projectile already went through another enemy
+        first_hit: bool,
+    },
+    EnemyKilled {
+        enemy_type: EnemyType,
+        pos: Vector2f,
+        angle: f32,
+        points: i32,
+        // kills in the combo chain, this one included
+        chain: i32,
+    },
+    // an enemy got past the bottom of the screen
+    EnemyBreached {
+        enemy_type: EnemyType,
+        pos: Vector2f,
+    },
+    // `wave_number` is the wave that was just cleared
+    WaveCleared {
+        wave_number: u32,
+    },
+    // an enemy reached the ship, which ends the run
+    PlayerHit {
+        pos: Vector2f,
+    },
+}
+
+pub trait Subscriber {
+    fn on_event(&mut self, event: &GameEvent);
+}
+
+// Collects the frame's events so they can be handed out in one place, in the
+// order they happened
+pub struct EventBus {
+    queue: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus { queue: Vec::new() }
+    }
+
+    pub fn publish(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    // every subscriber sees every event, then the queue is empty
+    pub fn dispatch(&mut self, subscribers: &mut [&mut dyn Subscriber]) {
+        for event in self.queue.drain(..) {
+            for subscriber in subscribers.iter_mut() {
+                subscriber.on_event(&event);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+// prints every event, turned on with --log-events
+pub struct EventLog {
+    pub enabled: bool,
+}
+
+impl Subscriber for EventLog {
+    fn on_event(&mut self, event: &GameEvent) {
+        if self.enabled {
+            eprintln!("[event] {:?}", event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Vec<GameEvent>,
+    }
+
+    impl Subscriber for Recorder {
+        fn on_event(&mut self, event: &GameEvent) {
+            self.seen.push(*event);
+        }
+    }
+
+    #[test]
+    fn every_subscriber_gets_every_event_in_order() {
+        let mut bus = EventBus::new();
+        let shot = GameEvent::ShotFired {
+            pos: Vector2f::new(1.0, 2.0),
+        };
+        let cleared = GameEvent::WaveCleared { wave_number: 3 };
+        bus.publish(shot);
+        bus.publish(cleared);
+
+        let mut first = Recorder::default();
+        let mut second = Recorder::default();
+        bus.dispatch(&mut [&mut first, &mut second]);
+        assert_eq!(first.seen, vec![shot, cleared]);
+        assert_eq!(second.seen, vec![shot, cleared]);
+    }
+
+    #[test]
+    fn events_are_only_delivered_once() {
+        let mut bus = EventBus::new();
+        bus.publish(GameEvent::WaveCleared { wave_number: 0 });
+        let mut recorder = Recorder::default();
+        bus.dispatch(&mut [&mut recorder]);
+        bus.dispatch(&mut [&mut recorder]);
+        assert_eq!(recorder.seen.len(), 1);
+    }
+}
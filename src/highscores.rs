@@ -0,0 +1,220 @@
+use std::cmp::Reverse;
+
+use crate::difficulty::{Difficulty, DifficultyParams};
+use crate::save::{self, write_file};
+
+// kept in the profile's folder, see profile.rs
+pub const HIGHSCORES_FILE: &str = "highscores.txt";
+// kept per difficulty, not overall
+const SCORES_KEPT: usize = 10;
+
+#[derive(Clone, Copy)]
+pub struct HighScore {
+    pub score: i32,
+    pub wave: u32,
+    pub difficulty: Difficulty,
+    pub params: DifficultyParams,
+}
+
+impl HighScore {
+    // two custom runs only count as the same difficulty if every parameter matches
+    pub fn same_difficulty(&self, difficulty: Difficulty, params: &DifficultyParams) -> bool {
+        self.difficulty == difficulty
+            && (difficulty != Difficulty::Custom || self.params == *params)
+    }
+}
+
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    // a missing file is no scores yet, and one that isn't text starts them
+    // over, see save::read; a broken line only loses that one score
+    pub fn load(path: &str) -> Self {
+        HighScores::parse(&save::read(path, |_| true).unwrap_or_default())
+    }
+
+    pub fn parse(text: &str) -> Self {
+        HighScores {
+            entries: text.lines().filter_map(parse_line).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) {
+        write_file(path, &self.format());
+    }
+
+    pub fn format(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(&format!(
+                "{} {} {}",
+                entry.difficulty.name(),
+                entry.score,
+                entry.wave
+            ));
+            if entry.difficulty == Difficulty::Custom {
+                for (key, value) in entry.params.fields() {
+                    text.push_str(&format!(" {}={}", key, value));
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn best(&self, difficulty: Difficulty, params: &DifficultyParams) -> Option<i32> {
+        self.entries
+            .iter()
+            .filter(|e| e.same_difficulty(difficulty, params))
+            .map(|e| e.score)
+            .max()
+    }
+
+    // returns true if the score beat the previous best for its difficulty
+    pub fn submit(&mut self, entry: HighScore) -> bool {
+        let is_best = self
+            .best(entry.difficulty, &entry.params)
+            .is_none_or(|best| entry.score > best);
+
+        self.entries.push(entry);
+        self.entries.sort_by_key(|e| Reverse(e.score));
+
+        // drop everything past the top SCORES_KEPT of this entry's difficulty
+        let mut kept = 0;
+        self.entries.retain(|e| {
+            if !e.same_difficulty(entry.difficulty, &entry.params) {
+                return true;
+            }
+            kept += 1;
+            kept <= SCORES_KEPT
+        });
+
+        is_best
+    }
+}
+
+// one score per line: `difficulty score wave`, custom runs add their `key=value` params
+fn parse_line(line: &str) -> Option<HighScore> {
+    let mut parts = line.split_whitespace();
+    let difficulty = parts.next().and_then(Difficulty::from_name)?;
+    let score = parts.next()?.parse().ok()?;
+    let wave = parts.next()?.parse().ok()?;
+
+    let mut params = difficulty.params(&DifficultyParams::NORMAL);
+    for (key, value) in parts.filter_map(|p| p.split_once('=')) {
+        params.set_field(key, value);
+    }
+    Some(HighScore {
+        score,
+        wave,
+        difficulty,
+        params,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(difficulty: Difficulty, score: i32) -> HighScore {
+        HighScore {
+            score,
+            wave: 1,
+            difficulty,
+            params: difficulty.params(&DifficultyParams::NORMAL),
+        }
+    }
+
+    #[test]
+    fn each_difficulty_has_its_own_best() {
+        let mut scores = HighScores::parse("");
+        assert!(scores.submit(entry(Difficulty::Hard, 500)));
+        assert!(scores.submit(entry(Difficulty::Easy, 100)));
+        assert!(!scores.submit(entry(Difficulty::Hard, 400)));
+        assert!(scores.submit(entry(Difficulty::Hard, 600)));
+        assert_eq!(
+            scores.best(Difficulty::Hard, &DifficultyParams::NORMAL),
+            Some(600)
+        );
+        assert_eq!(
+            scores.best(Difficulty::Easy, &DifficultyParams::NORMAL),
+            Some(100)
+        );
+        assert_eq!(
+            scores.best(Difficulty::Normal, &DifficultyParams::NORMAL),
+            None
+        );
+    }
+
+    #[test]
+    fn only_the_top_scores_of_a_difficulty_are_kept() {
+        let mut scores = HighScores::parse("");
+        scores.submit(entry(Difficulty::Easy, 5));
+        for score in 1..=SCORES_KEPT as i32 + 3 {
+            scores.submit(entry(Difficulty::Normal, score * 100));
+        }
+        let normal: Vec<i32> = scores
+            .entries
+            .iter()
+            .filter(|e| e.difficulty == Difficulty::Normal)
+            .map(|e| e.score)
+            .collect();
+        assert_eq!(normal.len(), SCORES_KEPT);
+        assert_eq!(normal.last(), Some(&400));
+        // another difficulty's low score isn't pushed out
+        assert_eq!(
+            scores.best(Difficulty::Easy, &DifficultyParams::NORMAL),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn custom_runs_only_compete_with_the_same_params() {
+        let mut scores = HighScores::parse("");
+        let mut tougher = DifficultyParams::NORMAL;
+        tougher.speed_scale = 2.0;
+        scores.submit(entry(Difficulty::Custom, 300));
+        assert!(scores.submit(HighScore {
+            params: tougher,
+            ..entry(Difficulty::Custom, 200)
+        }));
+        assert_eq!(scores.best(Difficulty::Custom, &tougher), Some(200));
+
+        let saved = scores.format();
+        assert_eq!(HighScores::parse(&saved).format(), saved);
+        assert_eq!(
+            HighScores::parse(&saved).best(Difficulty::Custom, &tougher),
+            Some(200)
+        );
+    }
+
+    #[test]
+    fn broken_lines_are_skipped() {
+        let scores = HighScores::parse("normal 100 2\nnightmare 5 1\nhard x 1\n");
+        assert_eq!(scores.entries.len(), 1);
+    }
+
+    #[test]
+    fn a_broken_line_keeps_the_other_scores_on_load() {
+        let dir = std::env::temp_dir().join(format!("highscores_test_{}", std::process::id()));
+        let path = dir.join(HIGHSCORES_FILE).to_string_lossy().into_owned();
+        write_file(
+            &path,
+            "normal 100 2
+normal x
+hard 700 5
+",
+        );
+
+        let scores = HighScores::load(&path);
+        assert_eq!(
+            scores.best(Difficulty::Hard, &DifficultyParams::NORMAL),
+            Some(700)
+        );
+        assert_eq!(scores.entries.len(), 2);
+        assert!(!std::path::Path::new(&format!("{}.bak", path)).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
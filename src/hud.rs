@@ -0,0 +1,326 @@
+use sfml::graphics::{
+    Color, Font, RectangleShape, RenderTarget, RenderWindow, Shape, Sprite, Text, Texture,
+    Transformable,
+};
+use sfml::system::Vector2f;
+use std::collections::VecDeque;
+
+use crate::WIDTH;
+use crate::combo::Combo;
+use crate::tween::{Ease, Tween};
+
+// the digit pngs in assets/numbers are 16x16
+const DIGIT_SIZE: f32 = 16.0;
+
+const SCORE_POSITION: Vector2f = Vector2f::new(16.0, 16.0);
+const COMBO_POSITION: Vector2f = Vector2f::new(16.0, 60.0);
+const COMBO_BAR_WIDTH: f32 = 120.0;
+const COMBO_COLOR: Color = Color::rgb(255, 210, 60);
+
+pub struct Hud<'a> {
+    digit_textures: Vec<&'a Texture>,
+    digit: Sprite<'a>,
+    bar: RectangleShape<'a>,
+}
+
+impl<'a> Hud<'a> {
+    pub fn new(digit_textures: Vec<&'a Texture>) -> Self {
+        Hud {
+            digit_textures,
+            digit: Sprite::new(),
+            bar: RectangleShape::new(),
+        }
+    }
+
+    // draws `value` left to right starting at `position`, returns the x where it ended
+    pub fn draw_number(
+        &mut self,
+        window: &mut RenderWindow,
+        value: i32,
+        position: Vector2f,
+        scale: f32,
+        color: Color,
+    ) -> f32 {
+        let mut x = position.x;
+        for c in value.max(0).to_string().chars() {
+            let index = c.to_digit(10).unwrap() as usize;
+            self.digit.set_texture(self.digit_textures[index], true);
+            self.digit.set_scale(scale);
+            self.digit.set_color(color);
+            self.digit.set_position(Vector2f::new(x, position.y));
+            window.draw(&self.digit);
+            x += DIGIT_SIZE * scale;
+        }
+        x
+    }
+
+    pub fn draw_number_centered(
+        &mut self,
+        window: &mut RenderWindow,
+        value: i32,
+        center: Vector2f,
+        scale: f32,
+        color: Color,
+    ) {
+        let width = value.max(0).to_string().len() as f32 * DIGIT_SIZE * scale;
+        let position = Vector2f::new(center.x - width / 2.0, center.y - DIGIT_SIZE * scale / 2.0);
+        self.draw_number(window, value, position, scale, color);
+    }
+
+    pub fn draw_score(&mut self, window: &mut RenderWindow, score: i32) {
+        self.draw_number(window, score, SCORE_POSITION, 2.0, Color::WHITE);
+    }
+
+    // "xN" followed by a bar showing how long the combo has left
+    pub fn draw_combo(&mut self, window: &mut RenderWindow, combo: &Combo) {
+        if combo.multiplier <= 1 && combo.timer <= 0.0 {
+            return;
+        }
+
+        // there is no "x" png, so the cross is made of two rotated bars
+        let cross_size = DIGIT_SIZE * 1.5;
+        let center = Vector2f::new(
+            COMBO_POSITION.x + cross_size / 2.0,
+            COMBO_POSITION.y + cross_size / 2.0 + 4.0,
+        );
+        self.bar.set_size(Vector2f::new(cross_size, 4.0));
+        self.bar.set_origin(Vector2f::new(cross_size / 2.0, 2.0));
+        self.bar.set_position(center);
+        self.bar.set_fill_color(COMBO_COLOR);
+        for angle in [45.0, -45.0] {
+            self.bar.set_rotation(angle);
+            window.draw(&self.bar);
+        }
+        self.bar.set_rotation(0.0);
+        self.bar.set_origin(Vector2f::new(0.0, 0.0));
+
+        self.draw_number(
+            window,
+            combo.multiplier,
+            Vector2f::new(COMBO_POSITION.x + cross_size + 4.0, COMBO_POSITION.y),
+            2.0,
+            COMBO_COLOR,
+        );
+
+        let bar_position =
+            Vector2f::new(COMBO_POSITION.x, COMBO_POSITION.y + DIGIT_SIZE * 2.0 + 6.0);
+        self.bar.set_position(bar_position);
+        self.bar.set_size(Vector2f::new(COMBO_BAR_WIDTH, 6.0));
+        self.bar.set_fill_color(Color::rgba(0, 0, 0, 140));
+        window.draw(&self.bar);
+        self.bar
+            .set_size(Vector2f::new(COMBO_BAR_WIDTH * combo.timer_fraction(), 6.0));
+        self.bar.set_fill_color(COMBO_COLOR);
+        window.draw(&self.bar);
+    }
+}
+
+const BANNER_CENTER: Vector2f = Vector2f::new(WIDTH as f32 / 2.0, 190.0);
+const BANNER_SLIDE_TIME: f32 = 0.45;
+const BANNER_HOLD_TIME: f32 = 1.0;
+const BANNER_FADE_TIME: f32 = 0.4;
+const BANNER_TEXT_SIZE: u32 = 56;
+
+// "Wave N" sliding in from the left at the start of every wave, flashing
+// from gold to white and then fading out
+pub struct WaveBanner<'a> {
+    text: Text<'a>,
+    slide: Tween<Vector2f>,
+    tint: Tween<Color>,
+    fade: Tween<f32>,
+}
+
+impl<'a> WaveBanner<'a> {
+    pub fn new(font: &'a Font) -> Self {
+        WaveBanner {
+            text: Text::new("", font, BANNER_TEXT_SIZE),
+            slide: Tween::at(BANNER_CENTER),
+            tint: Tween::at(Color::WHITE),
+            // starts out finished, so nothing shows until the first show()
+            fade: Tween::at(0.0),
+        }
+    }
+
+    // `message` comes from the string table, e.g. "Wave 3"
+    pub fn show(&mut self, message: &str) {
+        self.text.set_string(message);
+        let bounds = self.text.local_bounds();
+        self.text.set_origin(Vector2f::new(
+            bounds.left + bounds.width / 2.0,
+            bounds.top + bounds.height / 2.0,
+        ));
+
+        let hold = BANNER_SLIDE_TIME + BANNER_HOLD_TIME;
+        let start = Vector2f::new(-(WIDTH as f32) / 2.0, BANNER_CENTER.y);
+        self.slide = Tween::new(start, BANNER_CENTER, BANNER_SLIDE_TIME, Ease::OutBack);
+        self.tint = Tween::new(COMBO_COLOR, Color::WHITE, hold, Ease::InOutQuad);
+        self.fade = Tween::new(255.0, 0.0, BANNER_FADE_TIME, Ease::InQuad).with_delay(hold);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.slide.update(dt);
+        self.tint.update(dt);
+        self.fade.update(dt);
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow) {
+        let alpha = self.fade.value() as u8;
+        if alpha == 0 {
+            return;
+        }
+        let mut color = self.tint.value();
+        color.a = alpha;
+        self.text.set_position(self.slide.value());
+        self.text.set_fill_color(color);
+        window.draw(&self.text);
+    }
+}
+
+const SUMMARY_TOP: f32 = 225.0;
+const SUMMARY_ROWS: usize = 5;
+const SUMMARY_ROW_HEIGHT: f32 = 27.0;
+const SUMMARY_COLUMN_WIDTH: f32 = 240.0;
+const SUMMARY_GAP: f32 = 40.0;
+const SUMMARY_PADDING: f32 = 14.0;
+const SUMMARY_TEXT_SIZE: u32 = 18;
+
+// The end-of-run numbers on the game over screen: "label  value" rows in two
+// columns on a dark panel, filling the left column first
+pub struct SummaryPanel<'a> {
+    font: &'a Font,
+    rows: Vec<(Text<'a>, Text<'a>)>,
+    background: RectangleShape<'a>,
+}
+
+impl<'a> SummaryPanel<'a> {
+    pub fn new(font: &'a Font) -> Self {
+        let width = SUMMARY_COLUMN_WIDTH * 2.0 + SUMMARY_GAP + SUMMARY_PADDING * 2.0;
+        let height = SUMMARY_ROW_HEIGHT * SUMMARY_ROWS as f32 + SUMMARY_PADDING * 2.0;
+        let mut background = RectangleShape::with_size(Vector2f::new(width, height));
+        background.set_position(Vector2f::new(
+            (WIDTH as f32 - width) / 2.0,
+            SUMMARY_TOP - SUMMARY_PADDING,
+        ));
+        background.set_fill_color(Color::rgba(0, 0, 0, 150));
+        SummaryPanel {
+            font,
+            rows: Vec::new(),
+            background,
+        }
+    }
+
+    // (label, value) pairs, already translated and formatted
+    pub fn show(&mut self, rows: &[(String, String)]) {
+        let left = (WIDTH as f32 - SUMMARY_COLUMN_WIDTH * 2.0 - SUMMARY_GAP) / 2.0;
+        self.rows = rows
+            .iter()
+            .enumerate()
+            .map(|(i, (label, value))| {
+                let column = (i / SUMMARY_ROWS) as f32;
+                let x = left + column * (SUMMARY_COLUMN_WIDTH + SUMMARY_GAP);
+                let y = SUMMARY_TOP + (i % SUMMARY_ROWS) as f32 * SUMMARY_ROW_HEIGHT;
+
+                let mut label = Text::new(label, self.font, SUMMARY_TEXT_SIZE);
+                label.set_fill_color(Color::rgb(170, 170, 170));
+                label.set_position(Vector2f::new(x, y));
+                // values line up on their right edge
+                let mut value = Text::new(value, self.font, SUMMARY_TEXT_SIZE);
+                let bounds = value.local_bounds();
+                value.set_origin(Vector2f::new(bounds.left + bounds.width, 0.0));
+                value.set_position(Vector2f::new(x + SUMMARY_COLUMN_WIDTH, y));
+                (label, value)
+            })
+            .collect();
+    }
+
+    pub fn draw(&self, window: &mut RenderWindow) {
+        window.draw(&self.background);
+        for (label, value) in &self.rows {
+            window.draw(label);
+            window.draw(value);
+        }
+    }
+}
+
+const TOAST_SIZE: Vector2f = Vector2f::new(300.0, 62.0);
+const TOAST_MARGIN: f32 = 16.0;
+// top right, the score and combo are on the left
+const TOAST_TOP: f32 = 16.0;
+const TOAST_SLIDE_TIME: f32 = 0.3;
+const TOAST_HOLD_TIME: f32 = 2.5;
+
+// A card that slides in from the right edge when an achievement unlocks, holds
+// for a moment and slides back out. Unlocks that come in while one is showing
+// wait their turn.
+pub struct Toast<'a> {
+    heading: Text<'a>,
+    name: Text<'a>,
+    background: RectangleShape<'a>,
+    // (heading, name) still to show
+    queue: VecDeque<(String, String)>,
+    slide_in: Tween<f32>,
+    slide_out: Tween<f32>,
+}
+
+impl<'a> Toast<'a> {
+    pub fn new(font: &'a Font) -> Self {
+        let mut heading = Text::new("", font, 14);
+        heading.set_fill_color(COMBO_COLOR);
+        let mut background = RectangleShape::with_size(TOAST_SIZE);
+        background.set_fill_color(Color::rgba(20, 20, 30, 220));
+        background.set_outline_color(COMBO_COLOR);
+        background.set_outline_thickness(2.0);
+        Toast {
+            heading,
+            name: Text::new("", font, 20),
+            background,
+            queue: VecDeque::new(),
+            // both finished, so nothing shows until the first show()
+            slide_in: Tween::at(0.0),
+            slide_out: Tween::at(0.0),
+        }
+    }
+
+    pub fn show(&mut self, heading: &str, name: &str) {
+        self.queue
+            .push_back((heading.to_string(), name.to_string()));
+    }
+
+    fn is_showing(&self) -> bool {
+        !self.slide_out.is_finished()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.slide_in.update(dt);
+        self.slide_out.update(dt);
+        if self.is_showing() {
+            return;
+        }
+        let Some((heading, name)) = self.queue.pop_front() else {
+            return;
+        };
+        self.heading.set_string(&heading);
+        self.name.set_string(&name);
+        let travel = TOAST_SIZE.x + TOAST_MARGIN;
+        self.slide_in = Tween::new(travel, 0.0, TOAST_SLIDE_TIME, Ease::OutBack);
+        self.slide_out = Tween::new(0.0, travel, TOAST_SLIDE_TIME, Ease::InQuad)
+            .with_delay(TOAST_SLIDE_TIME + TOAST_HOLD_TIME);
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow) {
+        if !self.is_showing() {
+            return;
+        }
+        let x = WIDTH as f32 - TOAST_SIZE.x - TOAST_MARGIN
+            + self.slide_in.value()
+            + self.slide_out.value();
+        self.background.set_position(Vector2f::new(x, TOAST_TOP));
+        self.heading
+            .set_position(Vector2f::new(x + 12.0, TOAST_TOP + 8.0));
+        self.name
+            .set_position(Vector2f::new(x + 12.0, TOAST_TOP + 28.0));
+        window.draw(&self.background);
+        window.draw(&self.heading);
+        window.draw(&self.name);
+    }
+}
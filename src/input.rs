@@ -0,0 +1,524 @@
+use std::collections::HashSet;
+
+use sfml::window::joystick::{self, Axis};
+use sfml::window::{Event, Key, mouse};
+
+use crate::save::{self, is_key_values, write_file};
+use crate::settings::parse_key_values;
+
+// kept in the profile's folder, see profile.rs
+pub const BINDINGS_FILE: &str = "bindings.txt";
+
+// Everything the player can ask for, whatever the device. Gameplay asks
+// whether an action is held, menus get one action per key or button press.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Fire,
+    Confirm,
+    Back,
+    Pause,
+}
+
+impl Action {
+    const ALL: [Action; 8] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Fire,
+        Action::Confirm,
+        Action::Back,
+        Action::Pause,
+    ];
+
+    fn is_menu_action(&self) -> bool {
+        *self != Action::Fire
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Fire => "fire",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::Pause => "pause",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+}
+
+// WASD and the arrows both move, space fires in game and confirms in menus.
+// Escape is Back, which also pauses during a run.
+const DEFAULT_BINDINGS: [(Key, Action); 14] = [
+    (Key::W, Action::Up),
+    (Key::Up, Action::Up),
+    (Key::S, Action::Down),
+    (Key::Down, Action::Down),
+    (Key::A, Action::Left),
+    (Key::Left, Action::Left),
+    (Key::D, Action::Right),
+    (Key::Right, Action::Right),
+    (Key::Space, Action::Fire),
+    (Key::Space, Action::Confirm),
+    (Key::Enter, Action::Confirm),
+    (Key::Escape, Action::Back),
+    (Key::Backspace, Action::Back),
+    (Key::P, Action::Pause),
+];
+
+// the keys bindings.txt can name, written the way SFML spells them
+const BINDABLE_KEYS: [Key; 67] = [
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Escape,
+    Key::LControl,
+    Key::LShift,
+    Key::LAlt,
+    Key::RControl,
+    Key::RShift,
+    Key::RAlt,
+    Key::Space,
+    Key::Enter,
+    Key::Backspace,
+    Key::Tab,
+    Key::PageUp,
+    Key::PageDown,
+    Key::End,
+    Key::Home,
+    Key::Insert,
+    Key::Delete,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Down,
+    Key::Numpad0,
+    Key::Numpad1,
+    Key::Numpad2,
+    Key::Numpad3,
+    Key::Numpad4,
+    Key::Numpad5,
+    Key::Numpad6,
+    Key::Numpad7,
+    Key::Numpad8,
+    Key::Numpad9,
+];
+
+fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    BINDABLE_KEYS.into_iter().find(|k| key_name(*k) == name)
+}
+
+// the first gamepad is used, with the usual A / B / right bumper / start layout
+const GAMEPAD: u32 = 0;
+const GAMEPAD_CONFIRM: u32 = 0;
+const GAMEPAD_BACK: u32 = 1;
+const GAMEPAD_PAUSE: u32 = 7;
+const GAMEPAD_FIRE: [u32; 2] = [0, 5];
+// stick and d-pad positions go from -100 to 100
+const STICK_DEADZONE: f32 = 50.0;
+
+// Keys and buttons are tracked from the window's events rather than read from
+// the devices, so typing or clicking in another window never reaches the game.
+pub struct Input {
+    bindings: Vec<(Key, Action)>,
+    held_keys: HashSet<Key>,
+    // the right mouse button fires too
+    mouse_fire: bool,
+    focused: bool,
+    // which way the stick pointed at the last event, so holding it
+    // moves the menu focus once instead of on every tiny movement
+    stick_x: i8,
+    stick_y: i8,
+    // polls the gamepad, which has no events for being held; tests swap it out
+    gamepad: fn(Action) -> bool,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input {
+            bindings: DEFAULT_BINDINGS.to_vec(),
+            held_keys: HashSet::new(),
+            mouse_fire: false,
+            focused: true,
+            stick_x: 0,
+            stick_y: 0,
+            gamepad: gamepad_held,
+        }
+    }
+
+    // `action = Key Key ...` per line; actions the file leaves out keep their
+    // default keys, and a missing or corrupt file means all the defaults
+    pub fn load_bindings(&mut self, path: &str) {
+        let text = save::read(path, is_key_values).unwrap_or_default();
+        self.bindings = parse_bindings(&text);
+    }
+
+    pub fn save_bindings(&self, path: &str) {
+        write_file(path, &format_bindings(&self.bindings));
+    }
+
+    // keeps the held keys up to date, call it for every window event
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::KeyPressed { code, .. } => {
+                self.held_keys.insert(code);
+            }
+            Event::KeyReleased { code, .. } => {
+                self.held_keys.remove(&code);
+            }
+            Event::MouseButtonPressed {
+                button: mouse::Button::Right,
+                ..
+            } => self.mouse_fire = true,
+            Event::MouseButtonReleased {
+                button: mouse::Button::Right,
+                ..
+            } => self.mouse_fire = false,
+            // releases that happen while another window has focus never
+            // arrive, so everything counts as let go
+            Event::LostFocus => {
+                self.focused = false;
+                self.held_keys.clear();
+                self.mouse_fire = false;
+            }
+            Event::GainedFocus => self.focused = true,
+            _ => {}
+        }
+    }
+
+    // the menu action a window event stands for, if any
+    pub fn menu_action(&mut self, event: &Event) -> Option<Action> {
+        match *event {
+            Event::KeyPressed { code, .. } => self
+                .bindings
+                .iter()
+                .find(|(key, action)| *key == code && action.is_menu_action())
+                .map(|(_, action)| *action),
+            Event::JoystickButtonPressed {
+                joystickid: GAMEPAD,
+                button,
+            } => match button {
+                GAMEPAD_CONFIRM => Some(Action::Confirm),
+                GAMEPAD_BACK => Some(Action::Back),
+                GAMEPAD_PAUSE => Some(Action::Pause),
+                _ => None,
+            },
+            Event::JoystickMoved {
+                joystickid: GAMEPAD,
+                axis,
+                position,
+            } => {
+                let direction = stick_direction(position);
+                match axis {
+                    Axis::X | Axis::PovX => {
+                        let changed = direction != self.stick_x;
+                        self.stick_x = direction;
+                        match direction {
+                            -1 if changed => Some(Action::Left),
+                            1 if changed => Some(Action::Right),
+                            _ => None,
+                        }
+                    }
+                    Axis::Y | Axis::PovY => {
+                        let changed = direction != self.stick_y;
+                        self.stick_y = direction;
+                        match direction {
+                            -1 if changed => Some(Action::Up),
+                            1 if changed => Some(Action::Down),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // what gameplay sees this frame, taken once after the events are handled
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            held: Action::ALL
+                .into_iter()
+                .filter(|action| self.is_held(*action))
+                .collect(),
+        }
+    }
+
+    fn is_held(&self, action: Action) -> bool {
+        if !self.focused {
+            return false;
+        }
+        let key_held = self
+            .bindings
+            .iter()
+            .any(|(key, a)| *a == action && self.held_keys.contains(key));
+        let mouse_held = action == Action::Fire && self.mouse_fire;
+        key_held || mouse_held || (self.gamepad)(action)
+    }
+}
+
+// The actions held down at the start of a frame
+pub struct Snapshot {
+    held: Vec<Action>,
+}
+
+impl Snapshot {
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+}
+
+fn stick_direction(position: f32) -> i8 {
+    if position <= -STICK_DEADZONE {
+        -1
+    } else if position >= STICK_DEADZONE {
+        1
+    } else {
+        0
+    }
+}
+
+fn gamepad_held(action: Action) -> bool {
+    if !joystick::is_connected(GAMEPAD) {
+        return false;
+    }
+    let axis = |axis| stick_direction(joystick::axis_position(GAMEPAD, axis));
+    let button = |button| joystick::is_button_pressed(GAMEPAD, button);
+    match action {
+        Action::Up => axis(Axis::Y) < 0 || axis(Axis::PovY) < 0,
+        Action::Down => axis(Axis::Y) > 0 || axis(Axis::PovY) > 0,
+        Action::Left => axis(Axis::X) < 0 || axis(Axis::PovX) < 0,
+        Action::Right => axis(Axis::X) > 0 || axis(Axis::PovX) > 0,
+        Action::Fire => GAMEPAD_FIRE.iter().any(|b| button(*b)),
+        Action::Confirm => button(GAMEPAD_CONFIRM),
+        Action::Back => button(GAMEPAD_BACK),
+        Action::Pause => button(GAMEPAD_PAUSE),
+    }
+}
+
+fn parse_bindings(text: &str) -> Vec<(Key, Action)> {
+    let mut bindings = Vec::new();
+    let mut bound = Vec::new();
+    for (key, value) in parse_key_values(text) {
+        let Some(action) = Action::from_name(key) else {
+            eprintln!("Unknown action {} in the key bindings", key);
+            continue;
+        };
+        bound.push(action);
+        for name in value.split_whitespace() {
+            match key_from_name(name) {
+                Some(key) => bindings.push((key, action)),
+                None => eprintln!("Unknown key {} in the key bindings", name),
+            }
+        }
+    }
+    let defaults = DEFAULT_BINDINGS
+        .into_iter()
+        .filter(|(_, action)| !bound.contains(action));
+    bindings.extend(defaults);
+    bindings
+}
+
+fn format_bindings(bindings: &[(Key, Action)]) -> String {
+    let mut text = String::new();
+    for action in Action::ALL {
+        let keys: Vec<String> = bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(key, _)| key_name(*key))
+            .collect();
+        text.push_str(&format!("{}={}\n", action.name(), keys.join(" ")));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sfml::window::Scancode;
+
+    fn keys_for(bindings: &[(Key, Action)], action: Action) -> Vec<Key> {
+        bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    fn press(code: Key) -> Event {
+        Event::KeyPressed {
+            code,
+            scan: Scancode::Unknown,
+            alt: false,
+            ctrl: false,
+            shift: false,
+            system: false,
+        }
+    }
+
+    fn release(code: Key) -> Event {
+        Event::KeyReleased {
+            code,
+            scan: Scancode::Unknown,
+            alt: false,
+            ctrl: false,
+            shift: false,
+            system: false,
+        }
+    }
+
+    fn right_mouse_down() -> Event {
+        Event::MouseButtonPressed {
+            button: mouse::Button::Right,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    // an input that doesn't look at a real gamepad
+    fn input() -> Input {
+        Input {
+            gamepad: |_| false,
+            ..Input::new()
+        }
+    }
+
+    #[test]
+    fn pressed_keys_are_held_until_released() {
+        let mut input = input();
+        input.handle_event(&press(Key::Space));
+        input.handle_event(&press(Key::A));
+        let held = input.snapshot();
+        assert!(held.is_held(Action::Fire));
+        assert!(held.is_held(Action::Confirm));
+        assert!(held.is_held(Action::Left));
+        assert!(!held.is_held(Action::Right));
+
+        input.handle_event(&release(Key::Space));
+        let held = input.snapshot();
+        assert!(!held.is_held(Action::Fire));
+        assert!(held.is_held(Action::Left));
+    }
+
+    #[test]
+    fn the_right_mouse_button_fires() {
+        let mut input = input();
+        input.handle_event(&right_mouse_down());
+        assert!(input.snapshot().is_held(Action::Fire));
+    }
+
+    #[test]
+    fn losing_focus_lets_go_of_everything() {
+        let mut input = input();
+        input.handle_event(&press(Key::W));
+        input.handle_event(&right_mouse_down());
+        input.handle_event(&Event::LostFocus);
+        input.handle_event(&Event::GainedFocus);
+        // the releases went to the other window, they must not stay held
+        let held = input.snapshot();
+        assert!(!held.is_held(Action::Up));
+        assert!(!held.is_held(Action::Fire));
+    }
+
+    #[test]
+    fn nothing_is_held_without_focus() {
+        let mut input = Input {
+            gamepad: |action| action == Action::Fire,
+            ..Input::new()
+        };
+        assert!(input.snapshot().is_held(Action::Fire));
+        input.handle_event(&Event::LostFocus);
+        input.handle_event(&press(Key::D));
+        let held = input.snapshot();
+        assert!(!held.is_held(Action::Fire));
+        assert!(!held.is_held(Action::Right));
+    }
+
+    #[test]
+    fn menus_get_one_action_per_press() {
+        let mut input = input();
+        assert_eq!(input.menu_action(&press(Key::Space)), Some(Action::Confirm));
+        assert_eq!(input.menu_action(&press(Key::Escape)), Some(Action::Back));
+        assert_eq!(input.menu_action(&release(Key::Escape)), None);
+        let stick = |position| Event::JoystickMoved {
+            joystickid: GAMEPAD,
+            axis: Axis::Y,
+            position,
+        };
+        assert_eq!(input.menu_action(&stick(80.0)), Some(Action::Down));
+        // still held down, no repeat
+        assert_eq!(input.menu_action(&stick(90.0)), None);
+        assert_eq!(input.menu_action(&stick(0.0)), None);
+        assert_eq!(input.menu_action(&stick(-70.0)), Some(Action::Up));
+    }
+
+    #[test]
+    fn the_defaults_survive_a_round_trip() {
+        let text = format_bindings(&DEFAULT_BINDINGS);
+        let mut parsed = parse_bindings(&text);
+        let mut defaults = DEFAULT_BINDINGS.to_vec();
+        let order = |(key, action): &(Key, Action)| (*action as u8, *key as i32);
+        parsed.sort_by_key(order);
+        defaults.sort_by_key(order);
+        assert_eq!(parsed, defaults);
+    }
+
+    #[test]
+    fn actions_left_out_keep_their_default_keys() {
+        let bindings = parse_bindings("fire = LControl J\nup = I Dragon");
+        assert_eq!(
+            keys_for(&bindings, Action::Fire),
+            vec![Key::LControl, Key::J]
+        );
+        assert_eq!(keys_for(&bindings, Action::Up), vec![Key::I]);
+        assert_eq!(keys_for(&bindings, Action::Pause), vec![Key::P]);
+    }
+}
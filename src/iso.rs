@@ -0,0 +1,92 @@
+use sfml::system::Vector2f;
+
+// World space is a flat grid seen at an angle. +x is where D moves the ship
+// (down-right on screen), +y is where W moves it (up-right on screen) and
+// where enemies come from. The screen position of a world point is
+//
+//     ORIGIN + ((x + y) * ISO_X, (x - y) * ISO_Y)
+//
+// so a rectangle in world space shows up as a diamond on screen.
+
+// screen position of world (0, 0), which is also where the ship starts
+pub const ORIGIN: Vector2f = Vector2f::new(200.0, 390.0);
+// horizontal and vertical stretch of the projection, equal values give 45 degree axes
+pub const ISO_X: f32 = 1.0;
+pub const ISO_Y: f32 = 1.0;
+
+// the ship's playable area, a diamond once projected
+pub const PLAY_AREA_MIN: Vector2f = Vector2f::new(-75.0, -80.0);
+pub const PLAY_AREA_MAX: Vector2f = Vector2f::new(75.0, 80.0);
+
+pub fn world_to_screen(world: Vector2f) -> Vector2f {
+    ORIGIN + project_direction(world)
+}
+
+pub fn screen_to_world(screen: Vector2f) -> Vector2f {
+    let d = screen - ORIGIN;
+    let sum = d.x / ISO_X; // x + y
+    let difference = d.y / ISO_Y; // x - y
+    Vector2f::new((sum + difference) / 2.0, (sum - difference) / 2.0)
+}
+
+// a world space offset or direction as it looks on screen
+pub fn project_direction(world: Vector2f) -> Vector2f {
+    Vector2f::new((world.x + world.y) * ISO_X, (world.x - world.y) * ISO_Y)
+}
+
+pub fn clamp_to_play_area(world: Vector2f) -> Vector2f {
+    Vector2f::new(
+        world.x.clamp(PLAY_AREA_MIN.x, PLAY_AREA_MAX.x),
+        world.y.clamp(PLAY_AREA_MIN.y, PLAY_AREA_MAX.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_to_world_undoes_world_to_screen() {
+        for world in [
+            Vector2f::new(0.0, 0.0),
+            Vector2f::new(75.0, -80.0),
+            Vector2f::new(-12.5, 340.25),
+        ] {
+            assert_eq!(screen_to_world(world_to_screen(world)), world);
+        }
+        assert_eq!(world_to_screen(Vector2f::new(0.0, 0.0)), ORIGIN);
+    }
+
+    #[test]
+    fn world_axes_run_diagonally_on_screen() {
+        // D is down-right, W is up-right
+        assert_eq!(
+            project_direction(Vector2f::new(1.0, 0.0)),
+            Vector2f::new(ISO_X, ISO_Y)
+        );
+        assert_eq!(
+            project_direction(Vector2f::new(0.0, 1.0)),
+            Vector2f::new(ISO_X, -ISO_Y)
+        );
+        // directions don't move with the origin
+        let offset = Vector2f::new(3.0, -2.0);
+        assert_eq!(
+            world_to_screen(offset) - world_to_screen(Vector2f::new(0.0, 0.0)),
+            project_direction(offset)
+        );
+    }
+
+    #[test]
+    fn the_ship_is_kept_inside_the_play_area() {
+        let inside = Vector2f::new(10.0, -20.0);
+        assert_eq!(clamp_to_play_area(inside), inside);
+        assert_eq!(
+            clamp_to_play_area(Vector2f::new(500.0, -500.0)),
+            Vector2f::new(PLAY_AREA_MAX.x, PLAY_AREA_MIN.y)
+        );
+        assert_eq!(
+            clamp_to_play_area(Vector2f::new(-76.0, 81.0)),
+            Vector2f::new(PLAY_AREA_MIN.x, PLAY_AREA_MAX.y)
+        );
+    }
+}
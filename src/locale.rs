@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::settings::parse_key_values;
+
+pub const LOCALE_DIR: &str = "assets/locale";
+// every key exists in English, other languages may leave some out
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+// The string table for one language, read from assets/locale/<code>.txt as
+// `key = text` lines. Lookups fall back to English and then to the key
+// itself, so a missing translation shows up as its key instead of crashing.
+pub struct Strings {
+    table: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Strings {
+    pub fn load(language: &str) -> Self {
+        let read = |code: &str| {
+            let path = format!("{}/{}.txt", LOCALE_DIR, code);
+            fs::read_to_string(&path).unwrap_or_else(|e| {
+                eprintln!("Failed to load {}: {}", path, e);
+                String::new()
+            })
+        };
+        let fallback = read(FALLBACK_LANGUAGE);
+        let text = if language == FALLBACK_LANGUAGE {
+            String::new()
+        } else {
+            read(language)
+        };
+        Strings::parse(&text, &fallback)
+    }
+
+    fn parse(text: &str, fallback: &str) -> Self {
+        let table = |text| {
+            parse_key_values(text)
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+        Strings {
+            table: table(text),
+            fallback: table(fallback),
+        }
+    }
+
+    pub fn get<'s>(&'s self, key: &'s str) -> &'s str {
+        self.table
+            .get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    // the string with its `{}` replaced by `value`
+    pub fn format(&self, key: &str, value: impl ToString) -> String {
+        self.get(key).replacen("{}", &value.to_string(), 1)
+    }
+}
+
+// (code, name in that language) for every locale file, English first
+pub fn available_languages() -> Vec<(String, String)> {
+    let mut languages: Vec<(String, String)> = fs::read_dir(LOCALE_DIR)
+        .map(|dir| {
+            dir.filter_map(Result::ok)
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    let code = path.file_stem()?.to_str()?.to_string();
+                    let text = fs::read_to_string(&path).ok()?;
+                    let name = parse_key_values(&text)
+                        .find(|(key, _)| *key == "language.name")
+                        .map_or(code.clone(), |(_, name)| name.to_string());
+                    Some((code, name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    languages.sort_by_key(|(code, _)| (code != FALLBACK_LANGUAGE, code.clone()));
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENGLISH: &str = "
+        # comments and blank lines are skipped
+        play = Play
+        menu.back = Back
+        wave.banner = Wave {}
+    ";
+
+    #[test]
+    fn translation_wins_over_english() {
+        let strings = Strings::parse("play = Spielen", ENGLISH);
+        assert_eq!(strings.get("play"), "Spielen");
+    }
+
+    #[test]
+    fn missing_translation_falls_back_to_english() {
+        let strings = Strings::parse("play = Spielen", ENGLISH);
+        assert_eq!(strings.get("menu.back"), "Back");
+    }
+
+    #[test]
+    fn unknown_key_shows_the_key() {
+        let strings = Strings::parse("", ENGLISH);
+        assert_eq!(strings.get("no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn format_fills_in_the_value() {
+        let strings = Strings::parse("wave.banner = Vague {}", ENGLISH);
+        assert_eq!(strings.format("wave.banner", 3), "Vague 3");
+    }
+}
@@ -1,23 +1,72 @@
 extern crate sfml;
 
+mod achievements;
+mod animation;
+mod audio;
+mod camera;
+mod combo;
+mod difficulty;
+mod enemy;
+mod events;
+mod highscores;
+mod hud;
+mod input;
+mod iso;
+mod locale;
+mod menu;
+mod music;
+mod particles;
+mod profile;
+mod projectile;
+mod render;
+mod save;
+mod settings;
+mod stats;
+mod suspend;
+mod tween;
+mod ui;
+
+use achievements::{ACHIEVEMENTS_PATH, Achievements, UNLOCKED_FILE};
+use animation::{ANIMATIONS_PATH, AnimationLibrary, Animator, OneShot};
+use audio::{Audio, AudioBackend, NullBackend, SfmlBackend, SoundBank, Track};
+use camera::Camera;
+use combo::Combo;
+use difficulty::Difficulty;
+use enemy::{DamageOverlay, Enemy, EnemyAnimations, EnemyType, Wave, spawn_enemy, spawn_wave};
+use events::{EventBus, EventLog, GameEvent};
+use highscores::{HIGHSCORES_FILE, HighScore, HighScores};
+use hud::{Hud, SummaryPanel, Toast, WaveBanner};
+use input::{Action, BINDINGS_FILE, Input};
+use locale::Strings;
+use menu::{
+    AchievementsItem, GameOverItem, MainItem, MenuTextures, NewProfileItem, PauseItem,
+    ProfilesItem, SettingsItem,
+};
+use music::MusicState;
+use particles::{ContinuousEmitter, Emitters, ImpactEffects, PARTICLES_PATH, ParticleSystem};
+use profile::{MAX_NAME_LENGTH, PROFILES_PATH, Profiles};
+use projectile::ProjectilePool;
+use render::{Layer, RenderQueue};
+use save::SAVE_DIR;
+use settings::{SETTINGS_FILE, Settings};
 use sfml::graphics::*;
 use sfml::system::*;
-use sfml::window::joystick::BUTTON_COUNT;
 use sfml::window::*;
+use stats::{RUN_HISTORY_FILE, RunStats};
+use suspend::{SUSPENDED_RUN_FILE, SuspendedEnemy, SuspendedRun};
+use tween::Transition;
 
-use rand::Rng;
-use rand::seq::SliceRandom;
 use sfml::system::Clock;
 use std::collections::HashMap;
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 
-const BOUNDARY_TOP: f32 = 200.0;
-const BOUNDARY_BOTTOM: f32 = 550.0;
-const BOUNDARY_RIGHT: f32 = 350.0;
-const BOUNDARY_LEFT: f32 = 50.0;
-const BULLETS_COUNT: i32 = 5;
+// seconds to fade out of one screen, and again to fade into the next
+const FADE_TIME: f32 = 0.25;
+
+// the battery pngs go from battery0 (empty) to battery5 (full)
+const BATTERY_TEXTURES: i32 = 5;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ShipFacing {
@@ -25,163 +74,91 @@ enum ShipFacing {
     Left,
     Right,
 }
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-enum EnemyType {
-    Default,
-    Armored,
-    Fast,
-}
 
-impl EnemyType {
-    fn default_speed(&self) -> f32 {
-        match *self {
-            EnemyType::Default => 1.0,
-            EnemyType::Armored => 0.5,
-            EnemyType::Fast => 2.0,
-        }
-    }
-    fn default_health(&self) -> i32 {
-        match *self {
-            EnemyType::Default => 2,
-            EnemyType::Armored => 4,
-            EnemyType::Fast => 1,
-        }
-    }
-    fn points_gained(&self) -> i32 {
-        match *self {
-            EnemyType::Default => 200,
-            EnemyType::Armored => 400,
-            EnemyType::Fast => 150,
-        }
-    }
+// Everything the game scene draws, queued up each frame and drawn in layer
+// and depth order (see render.rs)
+enum DrawItem {
+    Background,
+    Ship,
+    Enemy(usize),
+    Projectile(Vector2f),
+    Effect(usize),
+    Particles,
+    Controls,
+    Battery,
+    Score,
+    Banner,
 }
 
-struct Projectile<'a> {
-    sprite: Sprite<'a>,
-    speed: f32,
-    direction: i32, // -1 = up, 1 = down
-    damage: i32,
+// which battery png to show, the charge is scaled so any battery size fills all five bars
+fn battery_texture_index(charge: i32, battery_size: i32) -> i32 {
+    (charge * BATTERY_TEXTURES + battery_size - 1) / battery_size
 }
 
-// ENEMY AND WAVES
-struct Enemy<'a> {
-    sprite: Sprite<'a>,
-    spawn_point: Vector2f,
-    enemy_type: EnemyType,
-    alive: bool,
-    speed: f32,
-    health: i32,
-}
-
-#[derive(Clone)]
-enum EnemyPositions {
-    A1,
-    B1,
-    C1,
-    D1,
-    A2,
-    B2,
-    C2,
-    A3,
-    B3,
-    A4,
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Menu,
+    Settings,
+    Game,
+    Paused,
+    GameOver,
+    Achievements,
+    Profiles,
+    NewProfile,
 }
 
-impl EnemyPositions {
-    fn return_in_order() -> &'static [EnemyPositions] {
-        use EnemyPositions::*;
-        static POSITIONS: [EnemyPositions; 10] = [A1, B1, C1, D1, A2, B2, C2, A3, B3, A4];
-        &POSITIONS
-    }
-    fn value(&self) -> Vector2f {
-        match *self {
-            EnemyPositions::A1 => Vector2f::new(WIDTH as f32 - 65.0, 1.0),
-            EnemyPositions::B1 => Vector2f::new(WIDTH as f32 - 135.0, 1.0),
-            EnemyPositions::C1 => Vector2f::new(WIDTH as f32 - 205.0, 1.0),
-            EnemyPositions::D1 => Vector2f::new(WIDTH as f32 - 275.0, 1.0),
-            EnemyPositions::A2 => Vector2f::new(WIDTH as f32 - 65.0, 71.0),
-            EnemyPositions::B2 => Vector2f::new(WIDTH as f32 - 135.0, 71.0),
-            EnemyPositions::C2 => Vector2f::new(WIDTH as f32 - 205.0, 71.0),
-            EnemyPositions::A3 => Vector2f::new(WIDTH as f32 - 65.0, 141.0),
-            EnemyPositions::B3 => Vector2f::new(WIDTH as f32 - 135.0, 141.0),
-            EnemyPositions::A4 => Vector2f::new(WIDTH as f32 - 65.0, 211.0),
-        }
+// Whether closing the window now leaves a run to continue: one that's being
+// played, paused or in the settings on top of the pause menu, and isn't fading
+// out to the game over screen, the menu or a restart
+fn run_in_progress(screen: Screen, heading_to: Option<Screen>, settings_return: Screen) -> bool {
+    let in_run = |screen| match screen {
+        Screen::Game | Screen::Paused => true,
+        Screen::Settings => settings_return == Screen::Paused,
+        _ => false,
+    };
+    match heading_to {
+        None => in_run(screen),
+        // only a restart fades from the pause menu into the game
+        Some(next) => in_run(screen) && in_run(next) && next != Screen::Game,
     }
 }
 
-struct Wave<'a> {
-    enemies: Vec<Enemy<'a>>,
-}
-
-fn spawn_wave<'a>(textures: &HashMap<EnemyType, &'a Texture>, wave_number: u32) -> Wave<'a> {
-    let mut rng = rand::thread_rng();
-    let positions = EnemyPositions::return_in_order();
-    let mut enemies = Vec::new();
-
-    // Spawn more enemies as wave_number increases
-    let enemy_count = (3 + wave_number as usize).min(positions.len());
-
-    for i in 0..enemy_count {
-        // Randomly pick a variant — more fast/tank as wave number increases
-        let variant = if rng.gen_bool(0.2 + 0.1 * wave_number as f64) {
-            EnemyType::Fast
-        } else if rng.gen_bool(0.1 + 0.05 * wave_number as f64) {
-            EnemyType::Armored
-        } else {
-            EnemyType::Default
-        };
-
-        let texture = textures[&variant];
-        let mut sprite = Sprite::new();
-        sprite.set_texture(texture, false);
-        sprite.set_position(positions[i].value());
-        sprite.set_scale(2.0);
-
-        enemies.push(Enemy {
-            sprite,
-            speed: variant.default_speed(),
-            health: variant.default_health(),
-            enemy_type: variant,
-            alive: true,
-            spawn_point: positions[i].value(),
-        });
+// the windowed mode is the playfield at 1:1 and can be resized from there
+fn window_mode(fullscreen: bool) -> (VideoMode, Style) {
+    if fullscreen {
+        (VideoMode::desktop_mode(), Style::FULLSCREEN)
+    } else {
+        (VideoMode::new(WIDTH, HEIGHT, 32), Style::DEFAULT)
     }
-
-    Wave { enemies }
 }
 
-fn shoot<'a>(
-    texture: &'a Texture,
-    start_pos: Vector2f,
-    speed: f32,
-    direction: i32,
-) -> Projectile<'a> {
-    let mut projectile = Sprite::new();
-    projectile.set_texture(texture, false);
-    projectile.set_scale(1.0);
-    projectile.set_rotation(-30.0);
-    projectile.set_position(start_pos);
-
-    Projectile {
-        sprite: projectile,
-        speed,
-        direction,
-        damage: 0,
-    }
+// switches between windowed and fullscreen to match the settings
+fn recreate_window(window: &mut RenderWindow, camera: &mut Camera, settings: &Settings) {
+    let (mode, style) = window_mode(settings.fullscreen);
+    window.recreate(mode, "Space Invaders+", style, &Default::default());
+    window.set_vertical_sync_enabled(true);
+    camera.resize(window.size(), settings.integer_scaling);
 }
 
 fn main() {
-    let mut is_game_over: bool = false;
-    let mut has_game_started: bool = false;
-    let mut settings_opened: bool = false;
-
-    let mut window = RenderWindow::new(
-        (WIDTH, HEIGHT),
-        "Space Invaders+",
-        Style::CLOSE,
-        &Default::default(),
-    )
-    .unwrap();
+    let mut screen = Screen::Menu;
+
+    // settings, bindings, scores and unlocks all belong to whoever is playing
+    save::migrate(SAVE_DIR);
+    let mut profiles = Profiles::load(PROFILES_PATH);
+    let mut settings = Settings::load(&profiles.current().path(SETTINGS_FILE));
+    let mut high_scores = HighScores::load(&profiles.current().path(HIGHSCORES_FILE));
+    // the difficulty and parameters of the run being played, which can differ
+    // from the settings when it's a continued one
+    let mut run_difficulty = settings.difficulty;
+    let mut params = settings.difficulty.params(&settings.custom);
+    // a run left when the window was closed, offered as Continue
+    let mut suspended = SuspendedRun::load(&profiles.current().path(SUSPENDED_RUN_FILE));
+    let mut is_new_best = false;
+
+    let (mode, style) = window_mode(settings.fullscreen);
+    let mut window =
+        RenderWindow::new(mode, "Space Invaders+", style, &Default::default()).unwrap();
     window.set_vertical_sync_enabled(true);
 
     // ------------------------------------ MAIN MENU DEFINITIONS ------------------------------------
@@ -190,51 +167,92 @@ fn main() {
         Texture::from_file("assets/background.png").expect("Failed to load background texture");
     let mut background = Sprite::new();
     background.set_texture(&background_texture, false);
-
-    // game_title definitions
-    let game_title_texture =
-        Texture::from_file("assets/gameTitle.png").expect("Failed to load background texture");
-    let mut game_title = Sprite::new();
-    game_title.set_texture(&game_title_texture, false);
-    game_title.set_origin(Vector2f::new(266.0, 30.0));
-    game_title.set_position(Vector2f::new(WIDTH as f32 / 2.0, 260.0));
-    game_title.set_scale(1.3);
-
-    // button_play definitions
-    let button_play_off_texture =
-        Texture::from_file("assets/playButtonOff.png").expect("Failed to load background texture");
-    let button_play_on_texture =
-        Texture::from_file("assets/playButtonOn.png").expect("Failed to load background texture");
-    let mut button_play = Sprite::new();
-    button_play.set_texture(&button_play_off_texture, false);
-    button_play.set_origin(Vector2f::new(250.0, 62.5));
-    button_play.set_position(Vector2f::new(WIDTH as f32 / 2.0, 425.0));
-
-    //buttonSettings definitions
-    /*let settings_texture =
-        Texture::from_file("assets/gear.png").expect("Failed to load background texture");
-    let mut button_settings = Sprite::new();
-    button_settings.set_texture(&settings_texture, false);
-    button_settings.set_origin(Vector2f::new(24.0, 24.0));
-    button_settings.set_position(Vector2f::new(WIDTH as f32 - 48.0, 48.0));*/
+    // slightly oversized copy for gameplay so shake and zoom never show the edges
+    let mut game_background = Sprite::new();
+    game_background.set_texture(&background_texture, false);
+    game_background.set_origin(Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0));
+    game_background.set_position(Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0));
+    game_background.set_scale(1.1);
+
+    // menus are declared in menu.rs, see ui.rs for the widgets
+    let menu_textures = MenuTextures::load();
+    let font = Font::from_file("assets/fonts/DejaVuSans-Bold.ttf").expect("Failed to load font");
+    let languages = locale::available_languages();
+    let mut strings = Strings::load(&settings.language);
+    let mut main_menu = menu::main_menu(
+        &menu_textures,
+        &font,
+        &strings,
+        settings.difficulty,
+        &profiles.current().name,
+        suspended.is_some(),
+    );
+    let mut settings_menu = menu::settings_menu(&font, &strings, &settings, &languages);
+    let mut game_over_menu = menu::game_over_menu(&font, &strings);
+    let mut pause_menu = menu::pause_menu(&font, &strings);
+    let mut achievements = Achievements::load(ACHIEVEMENTS_PATH);
+    achievements.load_unlocked(&profiles.current().path(UNLOCKED_FILE));
+    // rebuilt every time it opens so it shows what's been unlocked since
+    let mut achievements_menu = menu::achievements_menu(&font, &strings, &achievements);
+    let mut profiles_menu = menu::profiles_menu(&font, &strings, &profiles);
+    // the name typed so far on the new profile page
+    let mut new_name = String::new();
+    let mut new_profile_menu = menu::new_profile_menu(&font, &strings, &new_name);
+    // settings can be opened from the main menu or the pause menu and go back there
+    let mut settings_return = Screen::Menu;
+    let mut pause_veil = RectangleShape::with_size(Vector2f::new(WIDTH as f32, HEIGHT as f32));
+    pause_veil.set_fill_color(Color::rgba(0, 0, 0, 150));
+    let mut new_best_text = Text::new("", &font, 24);
+    new_best_text.set_fill_color(Color::rgb(255, 210, 60));
 
     // ------------------------------------ GAME DEFINITIONS ---------------------------------------
     let mut score: i32 = 0;
+    let mut combo = Combo::new();
+
+    let number_textures: Vec<_> = (0..10)
+        .map(|i| {
+            Texture::from_file(&format!("assets/numbers/{}.png", i))
+                .expect("Failed to load number texture")
+        })
+        .collect();
+    let mut hud = Hud::new(number_textures.iter().map(|t| &**t).collect());
+    let mut wave_banner = WaveBanner::new(&font);
+    let mut run_stats = RunStats::new();
+    let mut summary = SummaryPanel::new(&font);
+    let mut toast = Toast::new(&font);
+
+    // sprite sheets for the ship, enemies and effects, see animation.rs
+    let animations = AnimationLibrary::load(ANIMATIONS_PATH);
+    let enemy_animations = EnemyAnimations::load(&animations);
+    let ship_idle = animations
+        .id("ship_idle")
+        .expect("Missing animation ship_idle");
+    let ship_bank_left = animations
+        .id("ship_bank_left")
+        .expect("Missing animation ship_bank_left");
+    let ship_bank_right = animations
+        .id("ship_bank_right")
+        .expect("Missing animation ship_bank_right");
+    // shots still go off without it
+    let muzzle_flash = animations.id("muzzle_flash");
+    // muzzle flashes and death animations that are still playing
+    let mut effects: Vec<OneShot> = Vec::new();
 
     let mut temp_changed_ship = false;
     // player definitions
-    let ship_texture_default =
-        Texture::from_file("assets/ship.png").expect("Failed to load background texture");
-    let ship_texture_left =
-        Texture::from_file("assets/leftShip.png").expect("Failed to load left ship texture");
-    let ship_texture_right =
-        Texture::from_file("assets/rightShip.png").expect("Failed to load right ship texture");
-
     let mut ship = Sprite::new();
-    ship.set_texture(&ship_texture_default, false);
-    ship.set_scale(2.6);
+    let mut ship_animator = Animator::new(ship_idle);
+    let mut ship_facing = ShipFacing::Neutral;
+    let mut ship_scale = 2.6;
     let move_speed = 2.5;
-    ship.set_position(Vector2f::new(200.0, 400.0));
+    // the ship lives in world space (see iso.rs), the sprite follows its projection
+    let mut ship_world = Vector2f::new(0.0, 0.0);
+    ship_animator.apply(
+        &animations,
+        &mut ship,
+        iso::world_to_screen(ship_world),
+        ship_scale,
+    );
 
     //ball definitions
     let ball_indent = 15.0;
@@ -274,22 +292,20 @@ fn main() {
 
     let projectile_texture =
         Texture::from_file("assets/projectile.png").expect("Failed to load projectile texture");
-    let mut projectiles: Vec<Projectile> = Vec::new();
+    // every projectile is drawn with this one sprite, moved to each position in turn
+    let mut projectile_sprite = Sprite::new();
+    projectile_sprite.set_texture(&projectile_texture, false);
+    projectile_sprite.set_scale(1.0);
+    projectile_sprite.set_rotation(-30.0);
+    let mut projectiles = ProjectilePool::new();
     let mut temp_shot: bool = false;
-    let mut bullets_availiable = BUTTON_COUNT;
-    let mut reload_clock = Clock::start().expect("RESULT");
-    let reload_interval = 1.0;
-
-    let enemy_texture = Texture::from_file("assets/enemyDefault.png").unwrap();
-    let enemy_fast_texture = Texture::from_file("assets/enemyFast.png").unwrap();
-    let enemy_armored_texture = Texture::from_file("assets/enemyArmored.png").unwrap();
-    let mut enemies: Vec<Enemy> = Vec::new();
+    let mut bullets_availiable = params.battery_size;
+    // seconds since the last charge came back, only counted while the game runs
+    let mut reload_time = 0.0;
 
     let mut wave_number = 0;
-    let mut textures: HashMap<EnemyType, &Texture> = HashMap::new();
-    textures.insert(EnemyType::Default, &enemy_texture);
-    textures.insert(EnemyType::Fast, &enemy_fast_texture);
-    textures.insert(EnemyType::Armored, &enemy_armored_texture);
+    // every wave of a run is spawned from this, see enemy::spawn_wave
+    let mut run_seed: u64 = rand::random();
 
     let battery_texture_0 = Texture::from_file("assets/battery0.png").unwrap();
     let battery_texture_1 = Texture::from_file("assets/battery1.png").unwrap();
@@ -313,322 +329,925 @@ fn main() {
         HEIGHT as f32 - 24.0 * 3.0,
     ));
 
-    let mut current_wave = spawn_wave(&textures, wave_number);
-    // ----------------------- GAME OVER SCREEEN --------------------
-
-    let game_over_text_texture =
-        Texture::from_file("assets/gameOverText.png").expect("Failed to load projectile texture");
-    let mut game_over_text = Sprite::new();
-    game_over_text.set_texture(&game_over_text_texture, false);
-    game_over_text.set_origin(Vector2f::new(53.5, 26.0));
-    game_over_text.set_position(Vector2f::new(
-        WIDTH as f32 / 2.0,
-        HEIGHT as f32 / 2.0 - 150.0,
-    ));
-    game_over_text.set_scale(3.0);
-
-    let play_again_button_texture = Texture::from_file("assets/buttonPlayAgain.png")
-        .expect("Failed to load projectile texture");
-    let mut play_again_button = Sprite::new();
-    play_again_button.set_texture(&play_again_button_texture, false);
-    play_again_button.set_origin(Vector2f::new(40.0, 12.0));
-    play_again_button.set_position(Vector2f::new(
-        WIDTH as f32 / 2.0,
-        HEIGHT as f32 / 2.0 + 50.0,
-    ));
-    play_again_button.set_scale(4.0);
-
-    let menu_button_texture =
-        Texture::from_file("assets/menuButton.png").expect("Failed to load projectile texture");
-    let mut menu_button = Sprite::new();
-    menu_button.set_texture(&menu_button_texture, false);
-    menu_button.set_origin(Vector2f::new(32.0, 12.0));
-    menu_button.set_position(Vector2f::new(
-        WIDTH as f32 / 2.0,
-        HEIGHT as f32 / 2.0 + 180.0,
-    ));
-    menu_button.set_scale(4.0);
+    let mut damage_overlay = DamageOverlay::new();
+    let mut camera = Camera::new();
+    let mut render_queue: RenderQueue<DrawItem> = RenderQueue::new();
+    camera.resize(window.size(), settings.integer_scaling);
+
+    let emitters = Emitters::load(PARTICLES_PATH);
+    let mut particles = ParticleSystem::new();
+    let mut thruster = ContinuousEmitter::new();
+
+    let mut current_wave = spawn_wave(
+        &animations,
+        &enemy_animations,
+        wave_number,
+        &params,
+        run_seed,
+    );
+
+    // sound effects and music, silent with --no-audio or when the sounds can't be loaded
+    let sound_bank = SoundBank::load();
+    let audio_backend: Box<dyn AudioBackend> = match &sound_bank {
+        Some(bank) if !std::env::args().any(|arg| arg == "--no-audio") => {
+            Box::new(SfmlBackend::new(bank))
+        }
+        _ => Box::new(NullBackend),
+    };
+    let mut audio = Audio::new(audio_backend);
+    audio.mono = settings.mono_audio;
+
+    // gameplay publishes what happens, sound, particles and the camera react, see events.rs
+    let mut events = EventBus::new();
+    let mut event_log = EventLog {
+        enabled: std::env::args().any(|arg| arg == "--log-events"),
+    };
 
     //
     // =================== MAIN LOOP ====================
     //
 
+    let mut input = Input::new();
+    input.load_bindings(&profiles.current().path(BINDINGS_FILE));
+    let mut transition = Transition::new(FADE_TIME);
+    let mut fade_overlay = RectangleShape::with_size(Vector2f::new(WIDTH as f32, HEIGHT as f32));
+    let mut frame_clock = Clock::start().expect("Failed to start frame clock");
+
     loop {
+        let dt = frame_clock.restart().as_seconds();
+
         // events
+        let mut start_run = false;
+        let mut continue_run = false;
+        let mut switch_profile = false;
         while let Some(ev) = window.poll_event() {
+            let to_view = |pixel| camera.ui_coords(&window, pixel);
+            // menus take the mouse first, then keyboard and gamepad navigation
+            input.handle_event(&ev);
+            let action = input.menu_action(&ev);
+            let back = action == Some(Action::Back);
+            // menus stop listening once they start fading out
+            let listening = (!transition.is_leaving()).then_some(screen);
+            match listening {
+                Some(Screen::Menu) => match main_menu
+                    .handle_event(&ev, to_view)
+                    .or_else(|| main_menu.navigate(action?))
+                {
+                    Some(MainItem::Play) => start_run = true,
+                    Some(MainItem::Continue) => continue_run = true,
+                    Some(MainItem::Difficulty) => {
+                        let selected = main_menu.selected(MainItem::Difficulty);
+                        settings.difficulty = Difficulty::all()[selected];
+                        settings.save(&profiles.current().path(SETTINGS_FILE));
+                    }
+                    Some(MainItem::Settings) => {
+                        settings_return = Screen::Menu;
+                        transition.go_to(Screen::Settings);
+                    }
+                    Some(MainItem::Achievements) => {
+                        achievements_menu = menu::achievements_menu(&font, &strings, &achievements);
+                        transition.go_to(Screen::Achievements);
+                    }
+                    Some(MainItem::Profile) => {
+                        profiles_menu = menu::profiles_menu(&font, &strings, &profiles);
+                        transition.go_to(Screen::Profiles);
+                    }
+                    _ => {}
+                },
+                Some(Screen::Profiles) => match profiles_menu
+                    .handle_event(&ev, to_view)
+                    .or_else(|| profiles_menu.navigate(action?))
+                    .or(back.then_some(ProfilesItem::Back))
+                {
+                    Some(ProfilesItem::Profile(i)) => {
+                        profiles.select(i);
+                        switch_profile = true;
+                        transition.go_to(Screen::Menu);
+                    }
+                    Some(ProfilesItem::New) => {
+                        new_name.clear();
+                        new_profile_menu = menu::new_profile_menu(&font, &strings, &new_name);
+                        transition.go_to(Screen::NewProfile);
+                    }
+                    Some(ProfilesItem::Back) => transition.go_to(Screen::Menu),
+                    _ => {}
+                },
+                // typing goes into the name, so the keys that usually work
+                // menus don't, apart from Enter and Back
+                Some(Screen::NewProfile) => {
+                    let chosen = match ev {
+                        Event::TextEntered { unicode } if !unicode.is_control() => {
+                            if new_name.chars().count() < MAX_NAME_LENGTH {
+                                new_name.push(unicode);
+                            }
+                            None
+                        }
+                        Event::KeyPressed {
+                            code: Key::Backspace,
+                            ..
+                        } => {
+                            new_name.pop();
+                            None
+                        }
+                        Event::KeyPressed {
+                            code: Key::Enter, ..
+                        } => Some(NewProfileItem::Create),
+                        _ if back => Some(NewProfileItem::Back),
+                        _ => new_profile_menu.handle_event(&ev, to_view),
+                    };
+                    match chosen {
+                        // nothing happens while the name is empty or taken
+                        Some(NewProfileItem::Create) if profiles.add(&new_name).is_some() => {
+                            // the default bindings are written out so there's a file to edit
+                            Input::new().save_bindings(&profiles.current().path(BINDINGS_FILE));
+                            switch_profile = true;
+                            transition.go_to(Screen::Menu);
+                        }
+                        Some(NewProfileItem::Back) => transition.go_to(Screen::Profiles),
+                        _ => {}
+                    }
+                    if matches!(ev, Event::TextEntered { .. } | Event::KeyPressed { .. }) {
+                        new_profile_menu = menu::new_profile_menu(&font, &strings, &new_name);
+                    }
+                }
+                Some(Screen::Achievements) => {
+                    let chosen = achievements_menu
+                        .handle_event(&ev, to_view)
+                        .or_else(|| achievements_menu.navigate(action?))
+                        .or(back.then_some(AchievementsItem::Back));
+                    if chosen == Some(AchievementsItem::Back) {
+                        transition.go_to(Screen::Menu);
+                    }
+                }
+                Some(Screen::Settings) => match settings_menu
+                    .handle_event(&ev, to_view)
+                    .or_else(|| settings_menu.navigate(action?))
+                    .or(back.then_some(SettingsItem::Back))
+                {
+                    Some(SettingsItem::ScreenShake) => {
+                        settings.screen_shake = settings_menu.value(SettingsItem::ScreenShake);
+                        camera.shake_scale = settings.screen_shake;
+                    }
+                    Some(SettingsItem::HealthBars) => {
+                        settings.show_health_bars = settings_menu.is_on(SettingsItem::HealthBars);
+                    }
+                    Some(SettingsItem::HitStop) => {
+                        settings.hit_stop = settings_menu.is_on(SettingsItem::HitStop);
+                        camera.hit_stop_enabled = settings.hit_stop;
+                    }
+                    Some(SettingsItem::Fullscreen) => {
+                        settings.fullscreen = settings_menu.is_on(SettingsItem::Fullscreen);
+                        recreate_window(&mut window, &mut camera, &settings);
+                    }
+                    Some(SettingsItem::IntegerScaling) => {
+                        settings.integer_scaling =
+                            settings_menu.is_on(SettingsItem::IntegerScaling);
+                        camera.resize(window.size(), settings.integer_scaling);
+                    }
+                    Some(SettingsItem::MonoAudio) => {
+                        settings.mono_audio = settings_menu.is_on(SettingsItem::MonoAudio);
+                        audio.mono = settings.mono_audio;
+                    }
+                    // every menu is rebuilt so its text comes from the new table
+                    Some(SettingsItem::Language) => {
+                        let selected = settings_menu.selected(SettingsItem::Language);
+                        if let Some((code, _)) = languages.get(selected) {
+                            settings.language = code.clone();
+                        }
+                        strings = Strings::load(&settings.language);
+                        main_menu = menu::main_menu(
+                            &menu_textures,
+                            &font,
+                            &strings,
+                            settings.difficulty,
+                            &profiles.current().name,
+                            suspended.is_some(),
+                        );
+                        game_over_menu = menu::game_over_menu(&font, &strings);
+                        pause_menu = menu::pause_menu(&font, &strings);
+                        settings_menu = menu::settings_menu(&font, &strings, &settings, &languages);
+                        settings_menu.focus(SettingsItem::Language);
+                    }
+                    // changes apply straight away and are saved on the way out
+                    Some(SettingsItem::Back) => {
+                        settings.save(&profiles.current().path(SETTINGS_FILE));
+                        transition.go_to(settings_return);
+                    }
+                    _ => {}
+                },
+                Some(Screen::GameOver) => match game_over_menu
+                    .handle_event(&ev, to_view)
+                    .or_else(|| game_over_menu.navigate(action?))
+                    .or(back.then_some(GameOverItem::Menu))
+                {
+                    Some(GameOverItem::PlayAgain) => start_run = true,
+                    Some(GameOverItem::Menu) => transition.go_to(Screen::Menu),
+                    _ => {}
+                },
+                // pausing is instant, there's no fade over the game
+                Some(Screen::Game) => {
+                    let lost_focus = matches!(ev, Event::LostFocus);
+                    if back || action == Some(Action::Pause) || lost_focus {
+                        screen = Screen::Paused;
+                    }
+                }
+                Some(Screen::Paused) => match pause_menu
+                    .handle_event(&ev, to_view)
+                    .or_else(|| pause_menu.navigate(action?))
+                    .or((back || action == Some(Action::Pause)).then_some(PauseItem::Resume))
+                {
+                    Some(PauseItem::Resume) => {
+                        screen = Screen::Game;
+                        // confirming with the fire key shouldn't also shoot
+                        temp_shot = true;
+                    }
+                    Some(PauseItem::Restart) => start_run = true,
+                    Some(PauseItem::Settings) => {
+                        settings_return = Screen::Paused;
+                        transition.go_to(Screen::Settings);
+                    }
+                    Some(PauseItem::Quit) => transition.go_to(Screen::Menu),
+                    _ => {}
+                },
+                None => {}
+            }
+
             match ev {
+                // a run that's still going, paused or in the settings on top of
+                // the pause menu, is kept to be continued next time
                 Event::Closed => {
+                    if run_in_progress(screen, transition.heading_to(), settings_return) {
+                        let run = SuspendedRun {
+                            difficulty: run_difficulty,
+                            params,
+                            seed: run_seed,
+                            score,
+                            wave_number,
+                            ship: ship_world,
+                            battery: bullets_availiable,
+                            reload_time,
+                            enemies: current_wave
+                                .enemies
+                                .iter()
+                                .filter(|e| e.alive)
+                                .map(|e| SuspendedEnemy {
+                                    enemy_type: e.enemy_type,
+                                    world: e.world,
+                                    health: e.health,
+                                })
+                                .collect(),
+                            projectiles: projectiles.iter().copied().collect(),
+                            stats: run_stats.clone(),
+                            combo: combo.clone(),
+                            achievements: achievements.run_progress(),
+                        };
+                        run.save(&profiles.current().path(SUSPENDED_RUN_FILE));
+                    }
                     window.close();
                     return;
                 }
-                Event::MouseButtonReleased { button, x, y } => {
-                    if button == mouse::Button::Left {
-                        if !has_game_started && !settings_opened {
-                            let mouse_pos =
-                                window.map_pixel_to_coords(Vector2i::new(x, y), &window.view());
-
-                            if button_play.global_bounds().contains(mouse_pos) {
-                                has_game_started = true;
-                                button_play.set_texture(&button_play_off_texture, false);
-                                wave_number = 0;
-                                is_game_over = false;
-                                bullets_availiable = 5;
-                                ship.set_position(Vector2f::new(200.0, 400.0));
-                                current_wave = spawn_wave(&textures, wave_number);
-                            } /*  else if button_settings.global_bounds().contains(mouse_pos) {
-                        settings_opened = true;
-                        } */
-                        } else if has_game_started && is_game_over {
-                            let mouse_pos =
-                                window.map_pixel_to_coords(Vector2i::new(x, y), &window.view());
-                            if play_again_button.global_bounds().contains(mouse_pos) {
-                                wave_number = 0;
-                                is_game_over = false;
-                                bullets_availiable = 5;
-                                ship.set_position(Vector2f::new(200.0, 400.0));
-                                current_wave = spawn_wave(&textures, wave_number);
-                            } else if menu_button.global_bounds().contains(mouse_pos) {
-                                has_game_started = false;
-                                is_game_over = false;
-                            }
-                        }
-                    }
+                Event::Resized { width, height } => {
+                    camera.resize(Vector2u::new(width, height), settings.integer_scaling);
+                }
+                Event::KeyPressed { code: Key::F11, .. } => {
+                    settings.fullscreen = !settings.fullscreen;
+                    settings.save(&profiles.current().path(SETTINGS_FILE));
+                    settings_menu.set_on(SettingsItem::Fullscreen, settings.fullscreen);
+                    recreate_window(&mut window, &mut camera, &settings);
                 }
                 _ => {}
             }
         }
 
-        // drawing
-        window.clear(Color::BLACK);
-        if settings_opened {
-            // settings screen
-            window.draw(&background);
-        } else if !has_game_started {
-            // menu
-
-            let desktop_pos = mouse::desktop_position();
-            let window_pos = window.position();
-            let relative =
-                Vector2i::new(desktop_pos.x - window_pos.x, desktop_pos.y - window_pos.y);
-            let mouse_pos = window.map_pixel_to_coords(relative, &window.view());
-
-            if button_play.global_bounds().contains(mouse_pos) {
-                button_play.set_texture(&button_play_on_texture, false);
-                button_play.set_scale(1.13);
+        // everything the profile keeps is loaded and applied, and every menu
+        // rebuilt, as its language may differ
+        if switch_profile {
+            profiles.save(PROFILES_PATH);
+            let profile = profiles.current();
+            let was_fullscreen = settings.fullscreen;
+            settings = Settings::load(&profile.path(SETTINGS_FILE));
+            high_scores = HighScores::load(&profile.path(HIGHSCORES_FILE));
+            input.load_bindings(&profile.path(BINDINGS_FILE));
+            achievements.load_unlocked(&profile.path(UNLOCKED_FILE));
+            suspended = SuspendedRun::load(&profile.path(SUSPENDED_RUN_FILE));
+            audio.mono = settings.mono_audio;
+            if settings.fullscreen != was_fullscreen {
+                recreate_window(&mut window, &mut camera, &settings);
             } else {
-                button_play.set_texture(&button_play_off_texture, false);
-                button_play.set_scale(1.0);
+                camera.resize(window.size(), settings.integer_scaling);
             }
+            strings = Strings::load(&settings.language);
+            main_menu = menu::main_menu(
+                &menu_textures,
+                &font,
+                &strings,
+                settings.difficulty,
+                &profile.name,
+                suspended.is_some(),
+            );
+            settings_menu = menu::settings_menu(&font, &strings, &settings, &languages);
+            game_over_menu = menu::game_over_menu(&font, &strings);
+            pause_menu = menu::pause_menu(&font, &strings);
+        }
 
-            /*if button_settings.global_bounds().contains(mouse_pos) {
-                button_settings.set_scale(1.7);
-                button_settings.rotate(2.0);
-            } else {
-                button_settings.set_scale(1.5);
-            }*/
-
-            if game_title.global_bounds().contains(mouse_pos) {
-                game_title.set_scale(1.4);
-            } else {
-                game_title.set_scale(1.3);
-            }
-            window.draw(&background);
-            window.draw(&game_title);
-            window.draw(&button_play);
-            /*window.draw(&button_settings);*/
-        } else if !is_game_over {
-            // game
-
-            // ----------- GAME LOGIC -----------
-
-            let elapsed = reload_clock.elapsed_time().as_seconds();
-            if elapsed >= reload_interval {
-                if bullets_availiable < 5 {
-                    bullets_availiable += 1;
-                    battery.set_texture(textures_battery[&(bullets_availiable as i32)], false);
-                }
-                reload_clock.restart();
-            }
-            let mut ship_pos = ship.position();
-            let mut moved = false;
-
-            if Key::D.is_pressed() {
-                ship_pos.x += move_speed;
-                ship_pos.y += move_speed;
-                ship.set_texture(&ship_texture_right, true);
-                ship.set_scale(2.8);
-                ball.set_position(ball_position_6);
-                moved = true;
-                temp_changed_ship = true;
-            }
+        let held = input.snapshot();
 
-            if Key::A.is_pressed() {
-                ship_pos.x -= move_speed;
-                ship_pos.y -= move_speed;
-                ship.set_texture(&ship_texture_left, true);
-                ship.set_scale(2.4);
-                ball.set_position(ball_position_4);
-                moved = true;
-                temp_changed_ship = true;
-            }
+        if let Some(next) = transition.update(dt) {
+            screen = next;
+        }
 
-            if Key::W.is_pressed() {
-                ship_pos.x += move_speed;
-                ship_pos.y -= move_speed;
-                ship.set_texture(&ship_texture_default, true);
-                ship.set_scale(2.7);
-                moved = true;
-                ball.set_position(ball_position_2);
-                temp_changed_ship = true;
-            }
+        // play and play again both start a fresh run on the chosen difficulty,
+        // continue picks the suspended one up where it was left
+        if (start_run || continue_run) && !transition.is_leaving() {
+            let resumed = if continue_run { suspended.take() } else { None };
+            // whichever run starts, there's no going back to the suspended one
+            suspended = None;
+            SuspendedRun::discard(&profiles.current().path(SUSPENDED_RUN_FILE));
+            main_menu = menu::main_menu(
+                &menu_textures,
+                &font,
+                &strings,
+                settings.difficulty,
+                &profiles.current().name,
+                false,
+            );
 
-            if Key::S.is_pressed() {
-                ship_pos.x -= move_speed;
-                ship_pos.y += move_speed;
-                ship.set_texture(&ship_texture_default, true);
-                ship.set_scale(2.5);
-                moved = true;
-                ball.set_position(ball_position_8);
-                temp_changed_ship = true;
-            }
-            if !moved {
-                if temp_changed_ship {
-                    ship.set_texture(&ship_texture_default, true);
-                    ship.set_scale(2.6);
-                    ball.set_position(ball_position_5);
-                    temp_changed_ship = false;
+            transition.go_to(Screen::Game);
+            // the key that started the run may also be fire, it has to be let go first
+            temp_shot = true;
+            effects.clear();
+            projectiles.clear();
+            particles.clear();
+            camera.reset();
+            camera.shake_scale = settings.screen_shake;
+            camera.hit_stop_enabled = settings.hit_stop;
+            events.clear();
+            match resumed {
+                Some(run) => {
+                    run_difficulty = run.difficulty;
+                    params = run.params;
+                    run_seed = run.seed;
+                    wave_number = run.wave_number;
+                    score = run.score;
+                    bullets_availiable = run.battery;
+                    reload_time = run.reload_time;
+                    ship_world = run.ship;
+                    let enemies = run.enemies.iter().map(|saved| {
+                        let mut enemy = spawn_enemy(
+                            &animations,
+                            &enemy_animations,
+                            saved.enemy_type,
+                            saved.world,
+                            &params,
+                        );
+                        enemy.health = saved.health;
+                        enemy
+                    });
+                    current_wave = Wave {
+                        enemies: enemies.collect(),
+                    };
+                    for projectile in run.projectiles {
+                        projectiles.insert(projectile);
+                    }
+                    run_stats = run.stats;
+                    combo = run.combo;
+                    achievements.resume_run(&run.achievements);
+                }
+                None => {
+                    run_difficulty = settings.difficulty;
+                    params = settings.difficulty.params(&settings.custom);
+                    run_seed = rand::random();
+                    wave_number = 0;
+                    score = 0;
+                    bullets_availiable = params.battery_size;
+                    reload_time = 0.0;
+                    ship_world = Vector2f::new(0.0, 0.0);
+                    run_stats = RunStats::new();
+                    combo.reset();
+                    achievements.start_run();
+                    current_wave = spawn_wave(
+                        &animations,
+                        &enemy_animations,
+                        wave_number,
+                        &params,
+                        run_seed,
+                    );
                 }
             }
+            wave_banner.show(&strings.format("hud.wave", wave_number + 1));
+            battery.set_texture(
+                textures_battery[&battery_texture_index(bullets_availiable, params.battery_size)],
+                false,
+            );
+            ship.set_position(iso::world_to_screen(ship_world));
+        }
 
-            if ship_pos.x < BOUNDARY_LEFT {
-                ship_pos.x = BOUNDARY_LEFT;
+        // drawing
+        window.clear(Color::BLACK);
+        camera.apply_ui(&mut window);
+        match screen {
+            Screen::Settings => {
+                window.draw(&background);
+                settings_menu.update(dt);
+                settings_menu.draw(&mut window);
             }
-            if ship_pos.x > BOUNDARY_RIGHT {
-                ship_pos.x = BOUNDARY_RIGHT;
+            Screen::Menu => {
+                window.draw(&background);
+                main_menu.update(dt);
+                main_menu.draw(&mut window);
             }
-            if ship_pos.y < BOUNDARY_TOP {
-                ship_pos.y = BOUNDARY_TOP;
+            Screen::Achievements => {
+                window.draw(&background);
+                achievements_menu.update(dt);
+                achievements_menu.draw(&mut window);
             }
-            if ship_pos.y > BOUNDARY_BOTTOM {
-                ship_pos.y = BOUNDARY_BOTTOM;
+            Screen::Profiles => {
+                window.draw(&background);
+                profiles_menu.update(dt);
+                profiles_menu.draw(&mut window);
             }
-
-            ship.set_position(ship_pos);
-
-            if mouse::Button::Right.is_pressed() {
-                button.set_texture(&button_on_texture, false);
-                if !temp_shot && bullets_availiable > 0 {
-                    let ship_pos = ship.position();
-                    let projectile_start = Vector2f::new(ship_pos.x + 85.0, ship_pos.y + 0.0);
-                    let direction_code = 1;
-                    projectiles.push(shoot(
-                        &projectile_texture,
-                        projectile_start,
-                        8.0,
-                        direction_code,
-                    ));
-                    temp_shot = true;
-                    bullets_availiable -= 1;
-                    battery.set_texture(textures_battery[&(bullets_availiable as i32)], false);
-                }
-            } else {
-                button.set_texture(&button_off_texture, false);
-                temp_shot = false;
+            Screen::NewProfile => {
+                window.draw(&background);
+                new_profile_menu.update(dt);
+                new_profile_menu.draw(&mut window);
             }
-
-            window.draw(&background);
-
-            window.draw(&ship);
-            window.draw(&stand);
-            window.draw(&ball);
-            window.draw(&button);
-
-            for enemy in &mut current_wave.enemies {
-                let mut pos = enemy.sprite.position();
-                pos.y += enemy.speed;
-                pos.x -= enemy.speed;
-                enemy.sprite.set_position(pos);
-                if enemy.health <= 0 {
-                    enemy.alive = false;
-                    score += enemy.enemy_type.points_gained();
+            Screen::Game | Screen::Paused => {
+                // game
+                let running = screen == Screen::Game;
+                if running {
+                    camera.update(dt);
+                    wave_banner.update(dt);
                 }
-            }
 
-            current_wave
-                .enemies
-                .retain(|e| e.sprite.position().y < HEIGHT as f32 && e.alive == true);
+                // ----------- GAME LOGIC -----------
 
-            if current_wave.enemies.is_empty() {
-                wave_number += 1;
-                current_wave = spawn_wave(&textures, wave_number);
-            }
+                // nothing moves while paused, during hit-stop or while fading out to
+                // the game over screen
+                if running && !camera.is_frozen() && !transition.is_leaving() {
+                    combo.update(dt);
+                    run_stats.update(dt);
+                    particles.update(dt);
+                    for effect in &mut effects {
+                        effect.update(&animations, dt);
+                    }
+                    effects.retain(|e| !e.animator.is_finished());
+
+                    reload_time += dt;
+                    if reload_time >= params.reload_interval {
+                        if bullets_availiable < params.battery_size {
+                            bullets_availiable += 1;
+                            battery.set_texture(
+                                textures_battery[&battery_texture_index(
+                                    bullets_availiable,
+                                    params.battery_size,
+                                )],
+                                false,
+                            );
+                        }
+                        reload_time = 0.0;
+                    }
+                    let mut moved = false;
+
+                    if held.is_held(Action::Right) {
+                        ship_world.x += move_speed;
+                        ship_facing = ShipFacing::Right;
+                        ship_scale = 2.8;
+                        ball.set_position(ball_position_6);
+                        moved = true;
+                        temp_changed_ship = true;
+                    }
 
-            for enemy in &current_wave.enemies {
-                if ship
-                    .global_bounds()
-                    .intersection(&enemy.sprite.global_bounds())
-                    != None
-                {
-                    is_game_over = true;
-                }
-                window.draw(&enemy.sprite);
-            }
-            for projectile in &mut projectiles {
-                let mut pos = projectile.sprite.position();
-                pos.x += projectile.speed;
-                pos.y -= projectile.speed * projectile.direction as f32;
-                projectile.sprite.set_position(pos);
-                let proj_bounds: FloatRect = projectile.sprite.global_bounds();
-                for enemy in &mut current_wave.enemies {
-                    let enemy_bounds: FloatRect = enemy.sprite.global_bounds();
-
-                    if proj_bounds.intersection(&enemy_bounds) != None {
-                        enemy.health -= 1;
-                        projectile.damage += 1;
+                    if held.is_held(Action::Left) {
+                        ship_world.x -= move_speed;
+                        ship_facing = ShipFacing::Left;
+                        ship_scale = 2.4;
+                        ball.set_position(ball_position_4);
+                        moved = true;
+                        temp_changed_ship = true;
+                    }
+
+                    if held.is_held(Action::Up) {
+                        ship_world.y += move_speed;
+                        ship_facing = ShipFacing::Neutral;
+                        ship_scale = 2.7;
+                        moved = true;
+                        ball.set_position(ball_position_2);
+                        temp_changed_ship = true;
+                    }
+
+                    if held.is_held(Action::Down) {
+                        ship_world.y -= move_speed;
+                        ship_facing = ShipFacing::Neutral;
+                        ship_scale = 2.5;
+                        moved = true;
+                        ball.set_position(ball_position_8);
+                        temp_changed_ship = true;
+                    }
+                    if !moved && temp_changed_ship {
+                        ship_facing = ShipFacing::Neutral;
+                        ship_scale = 2.6;
+                        ball.set_position(ball_position_5);
+                        temp_changed_ship = false;
+                    }
+
+                    ship_world = iso::clamp_to_play_area(ship_world);
+                    let ship_pos = iso::world_to_screen(ship_world);
+                    ship_animator.play(match ship_facing {
+                        ShipFacing::Neutral => ship_idle,
+                        ShipFacing::Left => ship_bank_left,
+                        ShipFacing::Right => ship_bank_right,
+                    });
+                    ship_animator.update(&animations, dt);
+                    ship_animator.apply(&animations, &mut ship, ship_pos, ship_scale);
+                    // exhaust comes out of the back of the ship, opposite to where it shoots
+                    thruster.update(
+                        &mut particles,
+                        &emitters.thruster,
+                        ship_pos + Vector2f::new(20.0, 45.0),
+                        135.0,
+                        dt,
+                    );
+
+                    if held.is_held(Action::Fire) {
+                        button.set_texture(&button_on_texture, false);
+                        if !temp_shot && bullets_availiable > 0 {
+                            // the muzzle sits at the ship's nose, 85px right of the sprite's corner
+                            let projectile_start =
+                                iso::screen_to_world(ship_pos + Vector2f::new(85.0, 0.0));
+                            let direction_code = 1;
+                            if projectiles.shoot(projectile_start, 8.0, direction_code) {
+                                if let Some(muzzle_flash) = muzzle_flash {
+                                    effects.push(OneShot::new(
+                                        &animations,
+                                        muzzle_flash,
+                                        ship_pos + Vector2f::new(85.0, 0.0),
+                                        1.0,
+                                    ));
+                                }
+                                events.publish(GameEvent::ShotFired { pos: ship_pos });
+                                bullets_availiable -= 1;
+                                battery.set_texture(
+                                    textures_battery[&battery_texture_index(
+                                        bullets_availiable,
+                                        params.battery_size,
+                                    )],
+                                    false,
+                                );
+                            }
+                            temp_shot = true;
+                        } else if !temp_shot {
+                            events.publish(GameEvent::BatteryEmpty { pos: ship_pos });
+                            temp_shot = true;
+                        }
+                    } else {
+                        button.set_texture(&button_off_texture, false);
+                        temp_shot = false;
+                    }
+
+                    for enemy in &mut current_wave.enemies {
+                        enemy.update(&animations, dt);
+                        if enemy.health <= 0 {
+                            enemy.alive = false;
+                        }
+                    }
+
+                    let breached = |e: &Enemy| e.sprite.position().y >= HEIGHT as f32;
+                    for enemy in current_wave
+                        .enemies
+                        .iter()
+                        .filter(|e| e.alive && breached(e))
+                    {
+                        events.publish(GameEvent::EnemyBreached {
+                            enemy_type: enemy.enemy_type,
+                            pos: enemy.center(),
+                        });
+                    }
+                    current_wave.enemies.retain(|e| !breached(e) && e.alive);
+
+                    if current_wave.enemies.is_empty() {
+                        events.publish(GameEvent::WaveCleared { wave_number });
+                        wave_number += 1;
+                        current_wave = spawn_wave(
+                            &animations,
+                            &enemy_animations,
+                            wave_number,
+                            &params,
+                            run_seed,
+                        );
+                        wave_banner.show(&strings.format("hud.wave", wave_number + 1));
+                    }
+
+                    let crashed = current_wave.enemies.iter().any(|enemy| {
+                        ship.global_bounds()
+                            .intersection(&enemy.sprite.global_bounds())
+                            .is_some()
+                    });
+                    if crashed {
+                        transition.go_to(Screen::GameOver);
+                        events.publish(GameEvent::PlayerHit {
+                            pos: ship.position(),
+                        });
+                        combo.reset();
+                    }
+                    projectiles.update();
+                    for projectile in projectiles.iter_mut() {
+                        let proj_screen = iso::world_to_screen(projectile.position);
+                        projectile_sprite.set_position(proj_screen);
+                        let proj_bounds: FloatRect = projectile_sprite.global_bounds();
+                        let proj_direction = Vector2f::new(0.0, projectile.direction as f32);
+                        let screen_direction = iso::project_direction(proj_direction);
+                        let proj_angle = screen_direction.y.atan2(screen_direction.x).to_degrees();
+                        for enemy in &mut current_wave.enemies {
+                            let enemy_bounds: FloatRect = enemy.sprite.global_bounds();
+
+                            if proj_bounds.intersection(&enemy_bounds).is_some() {
+                                enemy.hit(proj_direction);
+                                projectile.damage += 1;
+                                events.publish(GameEvent::EnemyHit {
+                                    enemy_type: enemy.enemy_type,
+                                    pos: proj_screen,
+                                    angle: proj_angle,
+                                    first_hit: projectile.damage == 1,
+                                });
+                                if enemy.health == 0 {
+                                    projectile.kills += 1;
+                                    if let Some(death) = enemy_animations.death(enemy.enemy_type) {
+                                        effects.push(OneShot::new(
+                                            &animations,
+                                            death,
+                                            enemy.center(),
+                                            2.0,
+                                        ));
+                                    }
+                                    let points = combo.register_kill(
+                                        enemy.enemy_type.points_gained(),
+                                        projectile.kills,
+                                    );
+                                    score += points;
+                                    events.publish(GameEvent::EnemyKilled {
+                                        enemy_type: enemy.enemy_type,
+                                        pos: enemy.center(),
+                                        angle: proj_angle,
+                                        points,
+                                        chain: combo.chain,
+                                    });
+                                }
+                            }
+                        }
+                    }
+
+                    let screen = FloatRect::new(0.0, 0.0, WIDTH as f32, HEIGHT as f32);
+                    for _ in 0..projectiles.cull(|p| screen.contains(iso::world_to_screen(p))) {
+                        events.publish(GameEvent::ShotMissed);
+                    }
+
+                    events.dispatch(&mut [
+                        &mut audio,
+                        &mut camera,
+                        &mut ImpactEffects {
+                            particles: &mut particles,
+                            emitters: &emitters,
+                        },
+                        &mut run_stats,
+                        &mut achievements,
+                        &mut event_log,
+                    ]);
+
+                    let unlocked = achievements.take_new_unlocks();
+                    for id in &unlocked {
+                        toast.show(
+                            strings.get("achievements.unlocked"),
+                            strings.get(&format!("achievement.{}.name", id)),
+                        );
+                    }
+                    if !unlocked.is_empty() {
+                        achievements.save_unlocked(&profiles.current().path(UNLOCKED_FILE));
+                    }
+
+                    // the run's own events are counted by now, and so are the
+                    // points from kills in the frame the ship crashed
+                    if crashed {
+                        is_new_best = high_scores.submit(HighScore {
+                            score,
+                            wave: wave_number,
+                            difficulty: run_difficulty,
+                            params,
+                        });
+                        high_scores.save(&profiles.current().path(HIGHSCORES_FILE));
+                        run_stats.append_to_history(
+                            &profiles.current().path(RUN_HISTORY_FILE),
+                            run_difficulty,
+                            score,
+                        );
+                        summary.show(&stats::summary_rows(&run_stats, &strings));
                     }
                 }
-            }
 
-            projectiles.retain(|p| {
-                p.sprite.position().x > 0.0 && p.sprite.position().x < WIDTH as f32 && p.damage < 2
-            });
+                // ----------- DRAWING -----------
 
-            for projectile in &projectiles {
-                window.draw(&projectile.sprite);
-            }
-            window.draw(&battery);
-        } else {
-            // game over
-            let desktop_pos = mouse::desktop_position();
-            let window_pos = window.position();
-            let relative = Vector2f::new(
-                desktop_pos.x as f32 - window_pos.x as f32,
-                desktop_pos.y as f32 - window_pos.y as f32,
-            );
+                // world objects are sorted by the bottom edge of their sprite
+                let base = |bounds: FloatRect| bounds.top + bounds.height;
 
-            if play_again_button.global_bounds().contains(relative) {
-                play_again_button.set_scale(4.5);
-            } else {
-                play_again_button.set_scale(4.0);
-            }
+                render_queue.clear();
+                render_queue.push(Layer::Background, 0.0, DrawItem::Background);
+                render_queue.push(Layer::World, base(ship.global_bounds()), DrawItem::Ship);
+                for (i, enemy) in current_wave.enemies.iter().enumerate() {
+                    render_queue.push(
+                        Layer::World,
+                        base(enemy.sprite.global_bounds()),
+                        DrawItem::Enemy(i),
+                    );
+                }
+                for projectile in projectiles.iter() {
+                    let screen_pos = iso::world_to_screen(projectile.position);
+                    projectile_sprite.set_position(screen_pos);
+                    render_queue.push(
+                        Layer::World,
+                        base(projectile_sprite.global_bounds()),
+                        DrawItem::Projectile(screen_pos),
+                    );
+                }
+                for i in 0..effects.len() {
+                    render_queue.push(Layer::Effects, 0.0, DrawItem::Effect(i));
+                }
+                render_queue.push(Layer::Effects, 0.0, DrawItem::Particles);
+                render_queue.push(Layer::Hud, 0.0, DrawItem::Controls);
+                render_queue.push(Layer::Hud, 0.0, DrawItem::Battery);
+                render_queue.push(Layer::Hud, 0.0, DrawItem::Score);
+                render_queue.push(Layer::Hud, 0.0, DrawItem::Banner);
+
+                let mut using_world_view = None;
+                for (layer, item) in render_queue.sorted() {
+                    // controls and HUD stay put while the world shakes
+                    if using_world_view != Some(layer.follows_camera()) {
+                        if layer.follows_camera() {
+                            camera.apply_world(&mut window);
+                        } else {
+                            camera.apply_ui(&mut window);
+                        }
+                        using_world_view = Some(layer.follows_camera());
+                    }
 
-            if menu_button.global_bounds().contains(relative) {
-                menu_button.set_scale(4.5);
-            } else {
-                menu_button.set_scale(4.0);
-            }
+                    match *item {
+                        DrawItem::Background => window.draw(&game_background),
+                        DrawItem::Ship => window.draw(&ship),
+                        DrawItem::Enemy(i) => {
+                            let enemy = &current_wave.enemies[i];
+                            window.draw(&enemy.sprite);
+                            damage_overlay.draw(&mut window, enemy, settings.show_health_bars);
+                        }
+                        DrawItem::Projectile(screen_pos) => {
+                            projectile_sprite.set_position(screen_pos);
+                            window.draw(&projectile_sprite);
+                        }
+                        DrawItem::Effect(i) => window.draw(&effects[i].sprite),
+                        DrawItem::Particles => particles.draw(&mut window),
+                        DrawItem::Controls => {
+                            window.draw(&stand);
+                            window.draw(&ball);
+                            window.draw(&button);
+                        }
+                        DrawItem::Battery => window.draw(&battery),
+                        DrawItem::Banner => wave_banner.draw(&mut window),
+                        DrawItem::Score => {
+                            hud.draw_score(&mut window, score);
+                            hud.draw_combo(&mut window, &combo);
+                        }
+                    }
+                }
 
-            if game_over_text.global_bounds().contains(relative) {
-                game_over_text.set_scale(3.4);
-            } else {
-                game_over_text.set_scale(3.0);
+                if !running {
+                    camera.apply_ui(&mut window);
+                    window.draw(&pause_veil);
+                    pause_menu.update(dt);
+                    pause_menu.draw(&mut window);
+                }
+            }
+            Screen::GameOver => {
+                window.draw(&background);
+                game_over_menu.update(dt);
+                game_over_menu.draw(&mut window);
+
+                // this run's score, gold if it set a new best, with the best for this difficulty
+                // and the run's stats under it
+                let score_color = if is_new_best {
+                    Color::rgb(255, 210, 60)
+                } else {
+                    Color::WHITE
+                };
+                hud.draw_number_centered(
+                    &mut window,
+                    score,
+                    Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0 - 142.0),
+                    2.0,
+                    score_color,
+                );
+                if is_new_best {
+                    new_best_text.set_string(strings.get("game_over.new_best"));
+                    let bounds = new_best_text.local_bounds();
+                    new_best_text.set_origin(Vector2f::new(bounds.left + bounds.width / 2.0, 0.0));
+                    new_best_text.set_position(Vector2f::new(
+                        WIDTH as f32 / 2.0,
+                        HEIGHT as f32 / 2.0 - 192.0,
+                    ));
+                    window.draw(&new_best_text);
+                }
+                if let Some(best) = high_scores.best(run_difficulty, &params) {
+                    hud.draw_number_centered(
+                        &mut window,
+                        best,
+                        Vector2f::new(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0 - 104.0),
+                        1.0,
+                        Color::rgb(170, 170, 170),
+                    );
+                }
+                summary.draw(&mut window);
             }
-
-            window.draw(&background);
-            window.draw(&game_over_text);
-            window.draw(&play_again_button);
-            window.draw(&menu_button);
+        }
+        // the run's music carries on under the pause menu, game over is quiet
+        audio.set_music(match screen {
+            Screen::Menu
+            | Screen::Settings
+            | Screen::Achievements
+            | Screen::Profiles
+            | Screen::NewProfile => Some(Track::Menu),
+            Screen::Game | Screen::Paused => Some(Track::Game),
+            Screen::GameOver => None,
+        });
+        // the game music's stems follow how hectic the run is, see music.rs
+        audio.set_stem_levels(&music::stem_levels(&MusicState {
+            live_enemies: current_wave.enemies.len(),
+            wave_number,
+            battery_charge: bullets_availiable as f32 / params.battery_size as f32,
+            armored_enemies: current_wave
+                .enemies
+                .iter()
+                .filter(|e| e.enemy_type == EnemyType::Armored)
+                .count(),
+        }));
+        audio.update(dt);
+        audio.flush();
+
+        // unlock toasts finish sliding by even if the run has ended
+        camera.apply_ui(&mut window);
+        toast.update(dt);
+        toast.draw(&mut window);
+
+        // the fade sits on top of everything, whichever screen is showing
+        let fade_alpha = transition.overlay_alpha();
+        if fade_alpha > 0 {
+            camera.apply_ui(&mut window);
+            fade_overlay.set_fill_color(Color::rgba(0, 0, 0, fade_alpha));
+            window.draw(&fade_overlay);
         }
         window.display();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_run_is_kept_while_it_is_played_or_paused() {
+        assert!(run_in_progress(Screen::Game, None, Screen::Menu));
+        assert!(run_in_progress(Screen::Paused, None, Screen::Menu));
+        assert!(run_in_progress(Screen::Settings, None, Screen::Paused));
+        assert!(!run_in_progress(Screen::Settings, None, Screen::Menu));
+        assert!(!run_in_progress(Screen::Menu, None, Screen::Paused));
+    }
+
+    #[test]
+    fn a_run_being_left_is_not_kept() {
+        assert!(!run_in_progress(
+            Screen::Game,
+            Some(Screen::GameOver),
+            Screen::Menu
+        ));
+        // quit to menu and restart from the pause menu
+        assert!(!run_in_progress(
+            Screen::Paused,
+            Some(Screen::Menu),
+            Screen::Menu
+        ));
+        assert!(!run_in_progress(
+            Screen::Paused,
+            Some(Screen::Game),
+            Screen::Menu
+        ));
+    }
+
+    #[test]
+    fn going_between_the_pause_menu_and_its_settings_keeps_the_run() {
+        assert!(run_in_progress(
+            Screen::Paused,
+            Some(Screen::Settings),
+            Screen::Paused
+        ));
+        assert!(run_in_progress(
+            Screen::Settings,
+            Some(Screen::Paused),
+            Screen::Paused
+        ));
+    }
+}
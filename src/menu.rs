@@ -0,0 +1,473 @@
+use sfml::cpp::FBox;
+use sfml::graphics::{Color, Font, Texture};
+use sfml::system::Vector2f;
+
+use crate::achievements::Achievements;
+use crate::difficulty::Difficulty;
+use crate::locale::Strings;
+use crate::profile::Profiles;
+use crate::settings::Settings;
+use crate::ui::{ListItem, Menu};
+use crate::{HEIGHT, WIDTH};
+
+fn difficulty_color(difficulty: Difficulty) -> Color {
+    match difficulty {
+        Difficulty::Easy => Color::rgb(90, 200, 90),
+        Difficulty::Normal => Color::rgb(230, 200, 60),
+        Difficulty::Hard => Color::rgb(220, 70, 60),
+        Difficulty::Custom => Color::rgb(150, 90, 220),
+    }
+}
+
+fn load(path: &str) -> FBox<Texture> {
+    Texture::from_file(path).unwrap_or_else(|_| panic!("Failed to load {}", path))
+}
+
+// The pictures the menus are built from, everything else is text
+pub struct MenuTextures {
+    gear: FBox<Texture>,
+}
+
+impl MenuTextures {
+    pub fn load() -> Self {
+        MenuTextures {
+            gear: load("assets/gear.png"),
+        }
+    }
+}
+
+// the orange of the old title artwork
+const TITLE_COLOR: Color = Color::rgb(255, 171, 32);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MainItem {
+    Title,
+    Play,
+    Continue,
+    Difficulty,
+    Settings,
+    Achievements,
+    Profile,
+}
+
+// Title, play button, the row of difficulty tabs (Easy, Normal and Hard show
+// one to three pips, Custom the gear), the settings gear in one top corner and
+// the achievements in the other, with who's playing between them. Continue sits
+// between the title and play when there's a suspended run.
+pub fn main_menu<'a>(
+    textures: &'a MenuTextures,
+    font: &'a Font,
+    strings: &Strings,
+    selected: Difficulty,
+    profile_name: &str,
+    can_continue: bool,
+) -> Menu<'a, MainItem> {
+    let difficulties = Difficulty::all()
+        .iter()
+        .enumerate()
+        .map(|(i, d)| ListItem {
+            color: difficulty_color(*d),
+            pips: if *d == Difficulty::Custom { 0 } else { i + 1 },
+            icon: (*d == Difficulty::Custom).then_some(&*textures.gear),
+        })
+        .collect();
+    let selected = Difficulty::all()
+        .iter()
+        .position(|d| *d == selected)
+        .unwrap_or(0);
+
+    let mut menu = Menu::new()
+        .text_label(
+            MainItem::Title,
+            Vector2f::new(WIDTH as f32 / 2.0, 260.0),
+            strings.get("menu.title"),
+            font,
+            64,
+        )
+        .text_color(TITLE_COLOR)
+        .hover_scale(1.08)
+        .text_button(
+            MainItem::Play,
+            Vector2f::new(WIDTH as f32 / 2.0, 425.0),
+            strings.get("menu.play"),
+            font,
+            72,
+        )
+        .hover_scale(1.13)
+        .list(
+            MainItem::Difficulty,
+            Vector2f::new(WIDTH as f32 / 2.0, 540.0),
+            difficulties,
+            selected,
+        )
+        .button(
+            MainItem::Settings,
+            Vector2f::new(WIDTH as f32 - 48.0, 48.0),
+            &textures.gear,
+            &textures.gear,
+            1.5,
+        )
+        .hover_scale(1.7)
+        .text_button(
+            MainItem::Achievements,
+            Vector2f::new(110.0, 48.0),
+            strings.get("menu.achievements"),
+            font,
+            26,
+        )
+        .hover_scale(1.15)
+        .text_button(
+            MainItem::Profile,
+            Vector2f::new(WIDTH as f32 / 2.0, 48.0),
+            &strings.format("menu.profile", profile_name),
+            font,
+            26,
+        )
+        .hover_scale(1.15);
+    if can_continue {
+        menu = menu
+            .text_button(
+                MainItem::Continue,
+                Vector2f::new(WIDTH as f32 / 2.0, 340.0),
+                strings.get("menu.continue"),
+                font,
+                36,
+            )
+            .hover_scale(1.15);
+    }
+    menu
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProfilesItem {
+    Label,
+    // index into Profiles::list
+    Profile(usize),
+    New,
+    Back,
+}
+
+// one button per profile with the current one in gold, then room for a new one
+pub fn profiles_menu<'a>(
+    font: &'a Font,
+    strings: &Strings,
+    profiles: &Profiles,
+) -> Menu<'a, ProfilesItem> {
+    let center_x = WIDTH as f32 / 2.0;
+    let row = |i: usize| 140.0 + 50.0 * i as f32;
+
+    let mut menu = Menu::new().text_label(
+        ProfilesItem::Label,
+        Vector2f::new(center_x, 60.0),
+        strings.get("profiles.title"),
+        font,
+        48,
+    );
+    for (i, profile) in profiles.list().iter().enumerate() {
+        menu = menu
+            .text_button(
+                ProfilesItem::Profile(i),
+                Vector2f::new(center_x, row(i)),
+                &profile.name,
+                font,
+                32,
+            )
+            .hover_scale(1.15);
+        if i == profiles.current_index() {
+            menu = menu.text_color(Color::rgb(255, 210, 60));
+        }
+    }
+    if !profiles.is_full() {
+        menu = menu
+            .text_button(
+                ProfilesItem::New,
+                Vector2f::new(center_x, HEIGHT as f32 - 140.0),
+                strings.get("profiles.new"),
+                font,
+                32,
+            )
+            .hover_scale(1.15);
+    }
+    menu.text_button(
+        ProfilesItem::Back,
+        Vector2f::new(center_x, HEIGHT as f32 - 70.0),
+        strings.get("settings.back"),
+        font,
+        40,
+    )
+    .hover_scale(1.15)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NewProfileItem {
+    Label,
+    Create,
+    Back,
+}
+
+// rebuilt on every key press with the name typed so far
+pub fn new_profile_menu<'a>(
+    font: &'a Font,
+    strings: &Strings,
+    name: &str,
+) -> Menu<'a, NewProfileItem> {
+    let center_x = WIDTH as f32 / 2.0;
+    let center_y = HEIGHT as f32 / 2.0;
+    Menu::new()
+        .text_label(
+            NewProfileItem::Label,
+            Vector2f::new(center_x, center_y - 140.0),
+            strings.get("new_profile.title"),
+            font,
+            48,
+        )
+        .text_label(
+            NewProfileItem::Label,
+            Vector2f::new(center_x, center_y - 30.0),
+            &format!("{}_", name),
+            font,
+            40,
+        )
+        .text_color(Color::rgb(255, 210, 60))
+        .text_button(
+            NewProfileItem::Create,
+            Vector2f::new(center_x, center_y + 90.0),
+            strings.get("new_profile.create"),
+            font,
+            40,
+        )
+        .hover_scale(1.15)
+        .text_button(
+            NewProfileItem::Back,
+            Vector2f::new(center_x, center_y + 165.0),
+            strings.get("settings.back"),
+            font,
+            40,
+        )
+        .hover_scale(1.15)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GameOverItem {
+    Title,
+    PlayAgain,
+    Menu,
+}
+
+// the score, best and run summary are drawn between the title and the buttons
+pub fn game_over_menu<'a>(font: &'a Font, strings: &Strings) -> Menu<'a, GameOverItem> {
+    let center_x = WIDTH as f32 / 2.0;
+    let center_y = HEIGHT as f32 / 2.0;
+    Menu::new()
+        .text_label(
+            GameOverItem::Title,
+            Vector2f::new(center_x, center_y - 230.0),
+            strings.get("game_over.title"),
+            font,
+            64,
+        )
+        .hover_scale(1.1)
+        .text_button(
+            GameOverItem::PlayAgain,
+            Vector2f::new(center_x, center_y + 150.0),
+            strings.get("game_over.play_again"),
+            font,
+            44,
+        )
+        .hover_scale(1.15)
+        .text_button(
+            GameOverItem::Menu,
+            Vector2f::new(center_x, center_y + 225.0),
+            strings.get("game_over.menu"),
+            font,
+            44,
+        )
+        .hover_scale(1.15)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PauseItem {
+    Title,
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+// drawn over the frozen game, which shows through a dark veil
+pub fn pause_menu<'a>(font: &'a Font, strings: &Strings) -> Menu<'a, PauseItem> {
+    let center_x = WIDTH as f32 / 2.0;
+    let row = |i: usize| HEIGHT as f32 / 2.0 - 60.0 + 75.0 * i as f32;
+    let button = |menu: Menu<'a, PauseItem>, id, i: usize, key: &str| {
+        menu.text_button(
+            id,
+            Vector2f::new(center_x, row(i)),
+            strings.get(key),
+            font,
+            40,
+        )
+        .hover_scale(1.15)
+    };
+
+    let mut menu = Menu::new().text_label(
+        PauseItem::Title,
+        Vector2f::new(center_x, row(0) - 110.0),
+        strings.get("pause.title"),
+        font,
+        64,
+    );
+    menu = button(menu, PauseItem::Resume, 0, "pause.resume");
+    menu = button(menu, PauseItem::Restart, 1, "pause.restart");
+    menu = button(menu, PauseItem::Settings, 2, "pause.settings");
+    button(menu, PauseItem::Quit, 3, "pause.quit")
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SettingsItem {
+    Label,
+    ScreenShake,
+    HealthBars,
+    HitStop,
+    Fullscreen,
+    IntegerScaling,
+    Language,
+    MonoAudio,
+    Back,
+}
+
+// labels down the left, their controls lined up on the right;
+// `languages` is (code, name) from locale::available_languages
+pub fn settings_menu<'a>(
+    font: &'a Font,
+    strings: &Strings,
+    settings: &Settings,
+    languages: &[(String, String)],
+) -> Menu<'a, SettingsItem> {
+    let label_x = 260.0;
+    let control_x = 580.0;
+    let row = |i: usize| 130.0 + 50.0 * i as f32;
+    let label = |menu: Menu<'a, SettingsItem>, i: usize, key: &str| {
+        menu.text_label(
+            SettingsItem::Label,
+            Vector2f::new(label_x, row(i)),
+            strings.get(key),
+            font,
+            26,
+        )
+    };
+
+    let mut menu = Menu::new().text_label(
+        SettingsItem::Label,
+        Vector2f::new(WIDTH as f32 / 2.0, 60.0),
+        strings.get("settings.title"),
+        font,
+        48,
+    );
+    menu = label(menu, 0, "settings.screen_shake").slider(
+        SettingsItem::ScreenShake,
+        Vector2f::new(control_x, row(0)),
+        settings.screen_shake,
+    );
+    menu = label(menu, 1, "settings.health_bars").toggle(
+        SettingsItem::HealthBars,
+        Vector2f::new(control_x, row(1)),
+        settings.show_health_bars,
+    );
+    menu = label(menu, 2, "settings.hit_stop").toggle(
+        SettingsItem::HitStop,
+        Vector2f::new(control_x, row(2)),
+        settings.hit_stop,
+    );
+    menu = label(menu, 3, "settings.fullscreen").toggle(
+        SettingsItem::Fullscreen,
+        Vector2f::new(control_x, row(3)),
+        settings.fullscreen,
+    );
+    menu = label(menu, 4, "settings.pixel_scaling").toggle(
+        SettingsItem::IntegerScaling,
+        Vector2f::new(control_x, row(4)),
+        settings.integer_scaling,
+    );
+    let selected = languages
+        .iter()
+        .position(|(code, _)| *code == settings.language)
+        .unwrap_or(0);
+    menu = label(menu, 5, "settings.language").choice(
+        SettingsItem::Language,
+        Vector2f::new(control_x, row(5)),
+        languages.iter().map(|(_, name)| name.clone()).collect(),
+        selected,
+        font,
+    );
+    menu = label(menu, 6, "settings.mono_audio").toggle(
+        SettingsItem::MonoAudio,
+        Vector2f::new(control_x, row(6)),
+        settings.mono_audio,
+    );
+    menu.text_button(
+        SettingsItem::Back,
+        Vector2f::new(WIDTH as f32 / 2.0, row(8)),
+        strings.get("settings.back"),
+        font,
+        40,
+    )
+    .hover_scale(1.15)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AchievementsItem {
+    Label,
+    Back,
+}
+
+// gold names for the unlocked ones, grey for the rest, each over its description
+pub fn achievements_menu<'a>(
+    font: &'a Font,
+    strings: &Strings,
+    achievements: &Achievements,
+) -> Menu<'a, AchievementsItem> {
+    let center_x = WIDTH as f32 / 2.0;
+    let row = |i: usize| 130.0 + 62.0 * i as f32;
+    let unlocked_color = Color::rgb(255, 210, 60);
+    let locked_color = Color::rgb(130, 130, 130);
+
+    let mut menu = Menu::new().text_label(
+        AchievementsItem::Label,
+        Vector2f::new(center_x, 60.0),
+        strings.get("achievements.title"),
+        font,
+        48,
+    );
+    for (i, achievement) in achievements.list().iter().enumerate() {
+        let name_color = if achievements.is_unlocked(&achievement.id) {
+            unlocked_color
+        } else {
+            locked_color
+        };
+        menu = menu
+            .text_label(
+                AchievementsItem::Label,
+                Vector2f::new(center_x, row(i)),
+                strings.get(&format!("achievement.{}.name", achievement.id)),
+                font,
+                22,
+            )
+            .text_color(name_color)
+            .text_label(
+                AchievementsItem::Label,
+                Vector2f::new(center_x, row(i) + 24.0),
+                strings.get(&format!("achievement.{}.description", achievement.id)),
+                font,
+                16,
+            )
+            .text_color(locked_color);
+    }
+    menu.text_button(
+        AchievementsItem::Back,
+        Vector2f::new(center_x, HEIGHT as f32 - 70.0),
+        strings.get("settings.back"),
+        font,
+        40,
+    )
+    .hover_scale(1.15)
+}
@@ -0,0 +1,127 @@
+// How loud each layer of the game music should be, worked out from the state
+// of play. Audio fades the stems towards these levels, see audio.rs.
+
+// the game track's stems, in the order Track::Game lists their files
+pub const BASS: usize = 0;
+pub const DRUMS: usize = 1;
+pub const LEAD: usize = 2;
+pub const ALARM: usize = 3;
+pub const GAME_STEMS: usize = 4;
+
+// this many enemies on screen is as tense as a crowd gets
+const FULL_CROWD: f32 = 10.0;
+// waves keep raising the floor until this one
+const FULL_WAVE: f32 = 12.0;
+// charge fraction at which the battery alarm joins in
+const LOW_BATTERY: f32 = 0.2;
+// a wave down to at least this many armored enemies and nothing else is
+// fought like a boss
+const BOSS_FIGHT_ARMORED: usize = 3;
+
+// the parts of the game that the music listens to
+pub struct MusicState {
+    pub live_enemies: usize,
+    pub wave_number: u32,
+    // 0 is empty, 1 is full
+    pub battery_charge: f32,
+    // how many of the live enemies are armored
+    pub armored_enemies: usize,
+}
+
+impl MusicState {
+    pub fn is_boss_fight(&self) -> bool {
+        self.armored_enemies >= BOSS_FIGHT_ARMORED && self.armored_enemies == self.live_enemies
+    }
+}
+
+// 0 for an empty first wave up to 1 for a packed late one, or any boss fight
+pub fn intensity(state: &MusicState) -> f32 {
+    if state.is_boss_fight() {
+        return 1.0;
+    }
+    let crowd = (state.live_enemies as f32 / FULL_CROWD).min(1.0);
+    let progress = (state.wave_number as f32 / FULL_WAVE).min(1.0);
+    (0.6 * crowd + 0.4 * progress).clamp(0.0, 1.0)
+}
+
+// 0 below `from`, 1 above `to`, a straight line between
+fn ramp(value: f32, from: f32, to: f32) -> f32 {
+    ((value - from) / (to - from)).clamp(0.0, 1.0)
+}
+
+// the bass always plays, drums come in as things heat up and the lead on top
+// of them; the alarm is only about the battery
+pub fn stem_levels(state: &MusicState) -> [f32; GAME_STEMS] {
+    let intensity = intensity(state);
+    let mut levels = [0.0; GAME_STEMS];
+    levels[BASS] = 1.0;
+    levels[DRUMS] = ramp(intensity, 0.2, 0.45);
+    levels[LEAD] = ramp(intensity, 0.55, 0.8);
+    levels[ALARM] = if state.battery_charge <= LOW_BATTERY {
+        1.0
+    } else {
+        0.0
+    };
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(live_enemies: usize, wave_number: u32) -> MusicState {
+        MusicState {
+            live_enemies,
+            wave_number,
+            battery_charge: 1.0,
+            armored_enemies: 0,
+        }
+    }
+
+    #[test]
+    fn a_quiet_start_is_just_the_bass() {
+        assert_eq!(stem_levels(&state(2, 0)), [1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn a_packed_late_wave_plays_everything_but_the_alarm() {
+        assert_eq!(stem_levels(&state(12, 15)), [1.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn more_enemies_never_calms_things_down() {
+        let mut last = 0.0;
+        for enemies in 0..15 {
+            let now = intensity(&state(enemies, 3));
+            assert!(now >= last);
+            last = now;
+        }
+    }
+
+    #[test]
+    fn low_battery_sounds_the_alarm() {
+        let mut low = state(2, 0);
+        low.battery_charge = 0.1;
+        assert_eq!(stem_levels(&low)[ALARM], 1.0);
+    }
+
+    #[test]
+    fn boss_fights_are_full_intensity() {
+        let mut boss = state(3, 0);
+        boss.armored_enemies = 3;
+        assert!(boss.is_boss_fight());
+        assert_eq!(intensity(&boss), 1.0);
+        assert_eq!(stem_levels(&boss)[LEAD], 1.0);
+    }
+
+    #[test]
+    fn a_boss_fight_is_a_wave_of_only_armored_enemies() {
+        let mut mixed = state(4, 0);
+        mixed.armored_enemies = 3;
+        assert!(!mixed.is_boss_fight());
+        let mut stragglers = state(2, 0);
+        stragglers.armored_enemies = 2;
+        assert!(!stragglers.is_boss_fight());
+        assert!(!state(0, 0).is_boss_fight());
+    }
+}
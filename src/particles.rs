@@ -0,0 +1,379 @@
+use rand::Rng;
+use rand::rngs::ThreadRng;
+use sfml::graphics::{Color, PrimitiveType, RenderStates, RenderTarget, RenderWindow, Vertex};
+use sfml::system::Vector2f;
+use std::fs;
+
+use crate::events::{GameEvent, Subscriber};
+use crate::settings::parse_key_values;
+
+pub const PARTICLES_PATH: &str = "assets/particles.txt";
+// hard cap on live particles, bursts past it are cut short
+pub const PARTICLE_BUDGET: usize = 1024;
+
+// What one kind of particle looks like and how it moves. Pairs are (min, max)
+// ranges picked from at random for every particle.
+#[derive(Clone, Copy)]
+pub struct EmitterConfig {
+    pub count: usize, // particles per burst
+    pub rate: f32,    // particles per second for continuous emitters
+    pub spread: f32,  // degrees either side of the emit direction
+    pub speed: (f32, f32),
+    pub lifetime: (f32, f32),
+    pub size: (f32, f32),
+    pub start_color: Color,
+    pub end_color: Color,
+    pub gravity: f32, // pixels per second squared, downwards
+    pub drag: f32,    // fraction of velocity lost per second
+}
+
+impl EmitterConfig {
+    const DEFAULT: EmitterConfig = EmitterConfig {
+        count: 10,
+        rate: 0.0,
+        spread: 180.0,
+        speed: (40.0, 120.0),
+        lifetime: (0.3, 0.6),
+        size: (2.0, 4.0),
+        start_color: Color::WHITE,
+        end_color: Color::rgba(255, 255, 255, 0),
+        gravity: 0.0,
+        drag: 1.0,
+    };
+
+    fn set_field(&mut self, key: &str, value: &str) {
+        match key {
+            "count" => self.count = value.parse().unwrap_or(self.count),
+            "rate" => self.rate = parse_number(value).unwrap_or(self.rate),
+            // either side, so only the size of it matters
+            "spread" => self.spread = parse_number(value).map_or(self.spread, f32::abs),
+            "speed" => self.speed = parse_range(value).unwrap_or(self.speed),
+            "lifetime" => self.lifetime = parse_range(value).unwrap_or(self.lifetime),
+            "size" => self.size = parse_range(value).unwrap_or(self.size),
+            "start_color" => self.start_color = parse_color(value).unwrap_or(self.start_color),
+            "end_color" => self.end_color = parse_color(value).unwrap_or(self.end_color),
+            "gravity" => self.gravity = parse_number(value).unwrap_or(self.gravity),
+            "drag" => self.drag = parse_number(value).unwrap_or(self.drag),
+            _ => {}
+        }
+    }
+}
+
+// "NaN" and "inf" parse as f32, but the random ranges can't take them
+fn parse_number(value: &str) -> Option<f32> {
+    value.trim().parse().ok().filter(|n: &f32| n.is_finite())
+}
+
+fn parse_range(value: &str) -> Option<(f32, f32)> {
+    let (min, max) = value.split_once(',')?;
+    Some((parse_number(min)?, parse_number(max)?))
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let parts: Vec<u8> = value
+        .split(',')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match parts[..] {
+        [r, g, b] => Some(Color::rgb(r, g, b)),
+        [r, g, b, a] => Some(Color::rgba(r, g, b, a)),
+        _ => None,
+    }
+}
+
+// Every emitter the game uses, read from assets/particles.txt. Anything left
+// out of the file keeps EmitterConfig::DEFAULT.
+pub struct Emitters {
+    pub explosion: EmitterConfig,
+    pub debris: EmitterConfig,
+    pub spark: EmitterConfig,
+    pub thruster: EmitterConfig,
+}
+
+impl Emitters {
+    pub fn load(path: &str) -> Self {
+        let text = fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {}", path, e);
+            String::new()
+        });
+        Emitters::parse(&text)
+    }
+
+    // the file is split into `[name]` sections of `key = value` lines
+    pub fn parse(text: &str) -> Self {
+        let mut emitters = Emitters {
+            explosion: EmitterConfig::DEFAULT,
+            debris: EmitterConfig::DEFAULT,
+            spark: EmitterConfig::DEFAULT,
+            thruster: EmitterConfig::DEFAULT,
+        };
+
+        let mut section = "";
+        for line in text.lines().map(str::trim) {
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name;
+                continue;
+            }
+            let config = match section {
+                "explosion" => &mut emitters.explosion,
+                "debris" => &mut emitters.debris,
+                "spark" => &mut emitters.spark,
+                "thruster" => &mut emitters.thruster,
+                _ => continue,
+            };
+            for (key, value) in parse_key_values(line) {
+                config.set_field(key, value);
+            }
+        }
+        emitters
+    }
+}
+
+struct Particle {
+    position: Vector2f,
+    velocity: Vector2f,
+    age: f32,
+    lifetime: f32,
+    size: f32,
+    start_color: Color,
+    end_color: Color,
+    gravity: f32,
+    drag: f32,
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    // rebuilt every frame so all particles go out in a single draw call
+    vertices: Vec<Vertex>,
+    rng: ThreadRng,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        ParticleSystem {
+            particles: Vec::with_capacity(PARTICLE_BUDGET),
+            vertices: Vec::with_capacity(PARTICLE_BUDGET * 6),
+            rng: rand::rng(),
+        }
+    }
+
+    // `direction` is in degrees, 0 = right, 90 = down
+    pub fn burst(&mut self, config: &EmitterConfig, position: Vector2f, direction: f32) {
+        for _ in 0..config.count {
+            self.spawn(config, position, direction);
+        }
+    }
+
+    pub fn spawn(&mut self, config: &EmitterConfig, position: Vector2f, direction: f32) {
+        if self.particles.len() >= PARTICLE_BUDGET {
+            return;
+        }
+        let angle =
+            (direction + self.rng.random_range(-config.spread..=config.spread)).to_radians();
+        let speed = self.random_in(config.speed);
+        let lifetime = self.random_in(config.lifetime);
+        let size = self.random_in(config.size);
+
+        self.particles.push(Particle {
+            position,
+            velocity: Vector2f::new(angle.cos(), angle.sin()) * speed,
+            age: 0.0,
+            lifetime,
+            size,
+            start_color: config.start_color,
+            end_color: config.end_color,
+            gravity: config.gravity,
+            drag: config.drag,
+        });
+    }
+
+    fn random_in(&mut self, (min, max): (f32, f32)) -> f32 {
+        if max > min {
+            self.rng.random_range(min..max)
+        } else {
+            min
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let mut i = 0;
+        while i < self.particles.len() {
+            let p = &mut self.particles[i];
+            p.age += dt;
+            if p.age >= p.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            p.velocity.y += p.gravity * dt;
+            p.velocity *= (1.0 - p.drag * dt).max(0.0);
+            p.position += p.velocity * dt;
+            i += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow) {
+        self.vertices.clear();
+        for p in &self.particles {
+            let t = p.age / p.lifetime;
+            let color = lerp_color(p.start_color, p.end_color, t);
+            let half = p.size / 2.0;
+            let (left, right) = (p.position.x - half, p.position.x + half);
+            let (top, bottom) = (p.position.y - half, p.position.y + half);
+
+            // two triangles per particle
+            for (x, y) in [
+                (left, top),
+                (right, top),
+                (right, bottom),
+                (left, top),
+                (right, bottom),
+                (left, bottom),
+            ] {
+                self.vertices
+                    .push(Vertex::with_pos_color(Vector2f::new(x, y), color));
+            }
+        }
+        window.draw_primitives(
+            &self.vertices,
+            PrimitiveType::TRIANGLES,
+            &RenderStates::DEFAULT,
+        );
+    }
+}
+
+// Sparks where shots land and an explosion with debris where enemies die.
+// Made for the moment the frame's events are handed out.
+pub struct ImpactEffects<'a> {
+    pub particles: &'a mut ParticleSystem,
+    pub emitters: &'a Emitters,
+}
+
+impl Subscriber for ImpactEffects<'_> {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            // sparks fly back the way the shot came from
+            GameEvent::EnemyHit { pos, angle, .. } => {
+                self.particles
+                    .burst(&self.emitters.spark, pos, angle + 180.0);
+            }
+            GameEvent::EnemyKilled { pos, angle, .. } => {
+                self.particles.burst(&self.emitters.explosion, pos, 0.0);
+                self.particles.burst(&self.emitters.debris, pos, angle);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Emits a steady stream from a config's `rate`, carrying the leftover fraction
+// of a particle between frames.
+pub struct ContinuousEmitter {
+    accumulator: f32,
+}
+
+impl ContinuousEmitter {
+    pub fn new() -> Self {
+        ContinuousEmitter { accumulator: 0.0 }
+    }
+
+    pub fn update(
+        &mut self,
+        system: &mut ParticleSystem,
+        config: &EmitterConfig,
+        position: Vector2f,
+        direction: f32,
+        dt: f32,
+    ) {
+        self.accumulator += config.rate * dt;
+        while self.accumulator >= 1.0 {
+            system.spawn(config, position, direction);
+            self.accumulator -= 1.0;
+        }
+    }
+}
+
+fn lerp_color(from: Color, to: Color, t: f32) -> Color {
+    let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color::rgba(
+        lerp(from.r, to.r),
+        lerp(from.g, to.g),
+        lerp(from.b, to.b),
+        lerp(from.a, to.a),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_override_the_defaults() {
+        let emitters = Emitters::parse(
+            "# comment\n[spark]\ncount = 3\nspeed = 10, 20\nend_color = 1, 2, 3, 4\n\n[nonsense]\ncount = 99\n",
+        );
+        assert_eq!(emitters.spark.count, 3);
+        assert_eq!(emitters.spark.speed, (10.0, 20.0));
+        assert_eq!(emitters.spark.end_color, Color::rgba(1, 2, 3, 4));
+        assert_eq!(emitters.spark.spread, EmitterConfig::DEFAULT.spread);
+        assert_eq!(emitters.explosion.count, EmitterConfig::DEFAULT.count);
+    }
+
+    #[test]
+    fn bad_values_keep_the_default() {
+        let emitters = Emitters::parse(
+            "[debris]\nspread = NaN\nspeed = 10\nlifetime = 0.1, inf\nstart_color = 300, 0, 0\nrate = fast\n",
+        );
+        let debris = emitters.debris;
+        let default = EmitterConfig::DEFAULT;
+        assert_eq!(debris.spread, default.spread);
+        assert_eq!(debris.speed, default.speed);
+        assert_eq!(debris.lifetime, default.lifetime);
+        assert_eq!(debris.start_color, default.start_color);
+        assert_eq!(debris.rate, default.rate);
+    }
+
+    #[test]
+    fn odd_configs_still_spawn() {
+        let emitters = Emitters::parse("[spark]\nspread = -30\nspeed = 50, 10\nsize = 2, 2\n");
+        assert_eq!(emitters.spark.spread, 30.0);
+        let mut system = ParticleSystem::new();
+        system.burst(&emitters.spark, Vector2f::new(0.0, 0.0), 90.0);
+        assert_eq!(system.particles.len(), emitters.spark.count);
+    }
+
+    #[test]
+    fn bursts_stop_at_the_budget() {
+        let config = EmitterConfig {
+            count: PARTICLE_BUDGET / 2 + 1,
+            ..EmitterConfig::DEFAULT
+        };
+        let mut system = ParticleSystem::new();
+        system.burst(&config, Vector2f::new(0.0, 0.0), 0.0);
+        system.burst(&config, Vector2f::new(0.0, 0.0), 0.0);
+        assert_eq!(system.particles.len(), PARTICLE_BUDGET);
+
+        // once they die out there's room again
+        system.update(config.lifetime.1);
+        assert!(system.particles.is_empty());
+        system.burst(&config, Vector2f::new(0.0, 0.0), 0.0);
+        assert_eq!(system.particles.len(), config.count);
+    }
+
+    #[test]
+    fn continuous_emitters_carry_fractions_over() {
+        let config = EmitterConfig {
+            rate: 10.0,
+            ..EmitterConfig::DEFAULT
+        };
+        let mut system = ParticleSystem::new();
+        let mut emitter = ContinuousEmitter::new();
+        for _ in 0..4 {
+            emitter.update(&mut system, &config, Vector2f::new(0.0, 0.0), 0.0, 0.05);
+        }
+        assert_eq!(system.particles.len(), 2);
+    }
+}
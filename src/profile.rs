@@ -0,0 +1,180 @@
+use crate::save::{self, is_key_values, write_file};
+use crate::settings::parse_key_values;
+
+// the list of profiles and which one is playing
+pub const PROFILES_PATH: &str = "save/profiles.txt";
+// each profile keeps its files in a folder named after its id
+const PROFILES_DIR: &str = "save/profiles";
+pub const MAX_NAME_LENGTH: usize = 16;
+// as many as the profiles page has room for
+pub const MAX_PROFILES: usize = 6;
+// the one a fresh install starts with
+const FIRST_PROFILE_NAME: &str = "Player";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Profile {
+    // names the profile's folder, so the name itself can be anything
+    pub id: u32,
+    pub name: String,
+}
+
+impl Profile {
+    // e.g. profile.path(SETTINGS_FILE)
+    pub fn path(&self, file: &str) -> String {
+        format!("{}/{}/{}", PROFILES_DIR, self.id, file)
+    }
+}
+
+// Who can play on this machine. Each profile has its own settings, key
+// bindings, high scores, run history, achievements and suspended run.
+#[derive(PartialEq, Debug)]
+pub struct Profiles {
+    list: Vec<Profile>,
+    current: usize,
+}
+
+impl Profiles {
+    // save::migrate starts the list, so it's only missing if it was corrupt
+    pub fn load(path: &str) -> Self {
+        Profiles::parse(&save::read(path, is_key_values).unwrap_or_default())
+    }
+
+    // a `profile = id name` line per profile and `current = id`
+    pub fn parse(text: &str) -> Self {
+        let mut list = Vec::new();
+        let mut current_id = None;
+        for (key, value) in parse_key_values(text) {
+            match key {
+                "profile" => {
+                    let (id, name) = value.split_once(' ').unwrap_or((value, ""));
+                    if let Ok(id) = id.parse() {
+                        list.push(Profile {
+                            id,
+                            name: name.trim().to_string(),
+                        });
+                    }
+                }
+                "current" => current_id = value.parse().ok(),
+                _ => {}
+            }
+        }
+        if list.is_empty() {
+            list.push(Profile {
+                id: 1,
+                name: FIRST_PROFILE_NAME.to_string(),
+            });
+        }
+        let current = list
+            .iter()
+            .position(|p| Some(p.id) == current_id)
+            .unwrap_or(0);
+        Profiles { list, current }
+    }
+
+    pub fn format(&self) -> String {
+        let mut text = String::new();
+        for profile in &self.list {
+            text.push_str(&format!("profile={} {}\n", profile.id, profile.name));
+        }
+        text.push_str(&format!("current={}\n", self.current().id));
+        text
+    }
+
+    pub fn save(&self, path: &str) {
+        write_file(path, &self.format());
+    }
+
+    pub fn list(&self) -> &[Profile] {
+        &self.list
+    }
+
+    pub fn current(&self) -> &Profile {
+        &self.list[self.current]
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.list.len() >= MAX_PROFILES
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.list.len() {
+            self.current = index;
+        }
+    }
+
+    // adds a profile and selects it; the name is trimmed and cut to length, and
+    // None means it was empty, another profile already has it or there's no room
+    pub fn add(&mut self, name: &str) -> Option<&Profile> {
+        let name: String = name.trim().chars().take(MAX_NAME_LENGTH).collect();
+        let taken = self.list.iter().any(|p| p.name.eq_ignore_ascii_case(&name));
+        if name.is_empty() || taken || self.is_full() {
+            return None;
+        }
+        let id = self.list.iter().map(|p| p.id).max().unwrap_or(0) + 1;
+        self.list.push(Profile { id, name });
+        self.current = self.list.len() - 1;
+        Some(self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::SETTINGS_FILE;
+
+    #[test]
+    fn an_empty_list_starts_with_one_profile() {
+        let profiles = Profiles::parse("");
+        assert_eq!(profiles.list().len(), 1);
+        assert_eq!(profiles.current().name, FIRST_PROFILE_NAME);
+    }
+
+    #[test]
+    fn the_list_survives_a_round_trip() {
+        let mut profiles = Profiles::parse("");
+        profiles.add("Sam Lee").unwrap();
+        profiles.add("Jo").unwrap();
+        profiles.select(1);
+        assert_eq!(Profiles::parse(&profiles.format()), profiles);
+    }
+
+    #[test]
+    fn names_are_trimmed_and_unique() {
+        let mut profiles = Profiles::parse("");
+        assert!(profiles.add("   ").is_none());
+        assert!(profiles.add("player").is_none());
+        assert_eq!(profiles.add("  Jo ").unwrap().name, "Jo");
+        assert_eq!(profiles.current().name, "Jo");
+    }
+
+    #[test]
+    fn there_is_a_limit() {
+        let mut profiles = Profiles::parse("");
+        for i in 1..MAX_PROFILES {
+            assert!(profiles.add(&format!("P{}", i)).is_some());
+        }
+        assert!(profiles.is_full());
+        assert!(profiles.add("One more").is_none());
+    }
+
+    #[test]
+    fn new_profiles_get_the_next_id() {
+        let mut profiles = Profiles::parse("profile=1 Player\nprofile=4 Jo\ncurrent=4");
+        assert_eq!(profiles.current().name, "Jo");
+        assert_eq!(profiles.add("Sam").unwrap().id, 5);
+        assert_eq!(
+            profiles.current().path(SETTINGS_FILE),
+            "save/profiles/5/settings.txt"
+        );
+    }
+
+    #[test]
+    fn an_unknown_current_profile_falls_back_to_the_first() {
+        let profiles = Profiles::parse("profile=2 Jo\nprofile=3 Sam\ncurrent=9");
+        assert_eq!(profiles.current().name, "Jo");
+    }
+}
@@ -0,0 +1,183 @@
+use sfml::system::Vector2f;
+
+// enough for a full battery plus a few shots still flying from the previous one
+pub const PROJECTILE_CAPACITY: usize = 16;
+// a projectile can pierce through one enemy and dies on the second hit
+pub const MAX_HITS: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Projectile {
+    // world position, see iso.rs
+    pub position: Vector2f,
+    pub speed: f32,
+    pub direction: i32, // 1 = along world +y (away from the player), -1 = back towards it
+    pub damage: i32,
+    pub kills: i32,
+    pub alive: bool,
+}
+
+impl Projectile {
+    const DEAD: Projectile = Projectile {
+        position: Vector2f::new(0.0, 0.0),
+        speed: 0.0,
+        direction: 0,
+        damage: 0,
+        kills: 0,
+        alive: false,
+    };
+}
+
+// Fixed-size storage for every projectile in flight. Slots are reused, so
+// shooting and culling never allocate.
+pub struct ProjectilePool {
+    slots: [Projectile; PROJECTILE_CAPACITY],
+}
+
+impl ProjectilePool {
+    pub fn new() -> Self {
+        ProjectilePool {
+            slots: [Projectile::DEAD; PROJECTILE_CAPACITY],
+        }
+    }
+
+    // returns false when every slot is taken and the shot was dropped
+    pub fn shoot(&mut self, start_pos: Vector2f, speed: f32, direction: i32) -> bool {
+        self.insert(Projectile {
+            position: start_pos,
+            speed,
+            direction,
+            damage: 0,
+            kills: 0,
+            alive: true,
+        })
+    }
+
+    // puts a projectile back as it was, e.g. from a suspended run
+    pub fn insert(&mut self, projectile: Projectile) -> bool {
+        match self.slots.iter_mut().find(|p| !p.alive) {
+            Some(slot) => {
+                *slot = projectile;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn update(&mut self) {
+        for projectile in self.iter_mut() {
+            projectile.position.y += projectile.speed * projectile.direction as f32;
+        }
+    }
+
+    // kills projectiles that left the visible area on any side or used up their hits,
+    // returns how many of them left without hitting anything
+    pub fn cull(&mut self, is_visible: impl Fn(Vector2f) -> bool) -> usize {
+        let mut misses = 0;
+        for projectile in self.iter_mut() {
+            if !is_visible(projectile.position) {
+                projectile.alive = false;
+                if projectile.damage == 0 {
+                    misses += 1;
+                }
+            } else if projectile.damage >= MAX_HITS {
+                projectile.alive = false;
+            }
+        }
+        misses
+    }
+
+    pub fn clear(&mut self) {
+        for projectile in &mut self.slots {
+            projectile.alive = false;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Projectile> {
+        self.slots.iter().filter(|p| p.alive)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Projectile> {
+        self.slots.iter_mut().filter(|p| p.alive)
+    }
+
+    #[cfg(test)]
+    pub fn live_count(&self) -> usize {
+        self.iter().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a plain rectangle is enough here, the game passes the projected screen bounds
+    fn screen() -> impl Fn(Vector2f) -> bool {
+        |p: Vector2f| p.x > 0.0 && p.x < 800.0 && p.y > -600.0 && p.y < 600.0
+    }
+
+    #[test]
+    fn live_projectiles_never_exceed_capacity() {
+        let mut pool = ProjectilePool::new();
+        for _ in 0..1000 {
+            pool.shoot(Vector2f::new(285.0, 400.0), 8.0, 1);
+            pool.update();
+            pool.cull(screen());
+            assert!(pool.live_count() <= PROJECTILE_CAPACITY);
+        }
+    }
+
+    #[test]
+    fn full_pool_drops_new_shots() {
+        let mut pool = ProjectilePool::new();
+        for _ in 0..PROJECTILE_CAPACITY {
+            assert!(pool.shoot(Vector2f::new(100.0, 300.0), 8.0, 1));
+        }
+        assert!(!pool.shoot(Vector2f::new(100.0, 300.0), 8.0, 1));
+        assert_eq!(pool.live_count(), PROJECTILE_CAPACITY);
+    }
+
+    #[test]
+    fn shot_leaving_through_the_far_edge_is_culled() {
+        let mut pool = ProjectilePool::new();
+        // x stays inside the whole time, only y runs out
+        pool.shoot(Vector2f::new(10.0, 550.0), 8.0, 1);
+        for _ in 0..7 {
+            pool.update();
+            pool.cull(screen());
+        }
+        assert_eq!(pool.live_count(), 0);
+    }
+
+    #[test]
+    fn projectile_dies_after_max_hits() {
+        let mut pool = ProjectilePool::new();
+        pool.shoot(Vector2f::new(100.0, 300.0), 8.0, 1);
+        for projectile in pool.iter_mut() {
+            projectile.damage = MAX_HITS;
+        }
+        pool.cull(screen());
+        assert_eq!(pool.live_count(), 0);
+    }
+
+    #[test]
+    fn culled_slots_are_reused() {
+        let mut pool = ProjectilePool::new();
+        for _ in 0..PROJECTILE_CAPACITY {
+            pool.shoot(Vector2f::new(-5.0, 300.0), 8.0, 1);
+        }
+        pool.cull(screen());
+        assert_eq!(pool.live_count(), 0);
+        assert!(pool.shoot(Vector2f::new(100.0, 300.0), 8.0, 1));
+    }
+
+    #[test]
+    fn only_shots_that_hit_nothing_count_as_misses() {
+        let mut pool = ProjectilePool::new();
+        pool.shoot(Vector2f::new(-5.0, 300.0), 8.0, 1);
+        pool.shoot(Vector2f::new(-5.0, 300.0), 8.0, 1);
+        pool.shoot(Vector2f::new(100.0, 300.0), 8.0, 1);
+        pool.iter_mut().next().unwrap().damage = 1;
+        assert_eq!(pool.cull(screen()), 1);
+        assert_eq!(pool.live_count(), 1);
+    }
+}
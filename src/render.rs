@@ -0,0 +1,124 @@
+// Draw order for a frame. Things are pushed in any order and come back out
+// layer by layer; inside the world layer they are sorted by depth (the
+// screen y of their base) so whatever stands further forward in the
+// isometric scene covers what is behind it. Other layers keep the order
+// they were pushed in.
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Layer {
+    Background,
+    World,
+    // particles and other effects drawn over the whole world in one batch
+    Effects,
+    Hud,
+}
+
+impl Layer {
+    // everything but the HUD moves with the camera
+    pub fn follows_camera(&self) -> bool {
+        *self != Layer::Hud
+    }
+}
+
+pub struct RenderQueue<T> {
+    items: Vec<(Layer, f32, T)>,
+}
+
+impl<T> RenderQueue<T> {
+    pub fn new() -> Self {
+        RenderQueue { items: Vec::new() }
+    }
+
+    // the queue is reused every frame so it keeps its capacity
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    pub fn push(&mut self, layer: Layer, depth: f32, item: T) {
+        self.items.push((layer, depth, item));
+    }
+
+    // sorting is stable, so equal depths and non-world layers keep push order
+    pub fn sorted(&mut self) -> impl Iterator<Item = (Layer, &T)> {
+        self.items.sort_by(|a, b| {
+            a.0.cmp(&b.0).then_with(|| {
+                if a.0 == Layer::World {
+                    a.1.total_cmp(&b.1)
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+        });
+        self.items.iter().map(|(layer, _, item)| (*layer, item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draw_list(queue: &mut RenderQueue<&'static str>) -> Vec<&'static str> {
+        queue.sorted().map(|(_, item)| *item).collect()
+    }
+
+    #[test]
+    fn layers_draw_in_order_whatever_the_push_order() {
+        let mut queue = RenderQueue::new();
+        queue.push(Layer::Hud, 0.0, "score");
+        queue.push(Layer::World, 300.0, "ship");
+        queue.push(Layer::Effects, 0.0, "particles");
+        queue.push(Layer::Background, 0.0, "background");
+
+        assert_eq!(
+            draw_list(&mut queue),
+            ["background", "ship", "particles", "score"]
+        );
+    }
+
+    #[test]
+    fn world_is_sorted_back_to_front() {
+        let mut queue = RenderQueue::new();
+        queue.push(Layer::World, 450.0, "ship");
+        queue.push(Layer::World, 120.0, "enemy far back");
+        queue.push(Layer::World, 520.0, "enemy in front");
+        queue.push(Layer::World, 300.0, "projectile");
+
+        assert_eq!(
+            draw_list(&mut queue),
+            ["enemy far back", "projectile", "ship", "enemy in front"]
+        );
+    }
+
+    #[test]
+    fn enemy_behind_the_ship_never_covers_it() {
+        // the bug this replaced: enemies were always drawn after the ship
+        let mut queue = RenderQueue::new();
+        queue.push(Layer::World, 400.0, "ship");
+        queue.push(Layer::World, 350.0, "enemy");
+
+        let list = draw_list(&mut queue);
+        let ship = list.iter().position(|i| *i == "ship").unwrap();
+        let enemy = list.iter().position(|i| *i == "enemy").unwrap();
+        assert!(enemy < ship);
+    }
+
+    #[test]
+    fn hud_ignores_depth_and_keeps_push_order() {
+        let mut queue = RenderQueue::new();
+        queue.push(Layer::Hud, 900.0, "controls");
+        queue.push(Layer::Hud, 10.0, "battery");
+        queue.push(Layer::Hud, 500.0, "score");
+
+        assert_eq!(draw_list(&mut queue), ["controls", "battery", "score"]);
+    }
+
+    #[test]
+    fn queue_is_empty_after_clear() {
+        let mut queue = RenderQueue::new();
+        queue.push(Layer::World, 1.0, "enemy");
+        queue.clear();
+        queue.push(Layer::Hud, 0.0, "score");
+
+        assert_eq!(draw_list(&mut queue), ["score"]);
+    }
+}
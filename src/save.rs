@@ -0,0 +1,316 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use crate::settings::parse_key_values;
+
+// Everything the game writes lives in here: the profile list, and a folder
+// per profile with its settings, bindings, scores, history, unlocks and
+// suspended run
+pub const SAVE_DIR: &str = "save";
+// holds the schema version of the whole folder
+const VERSION_FILE: &str = "version.txt";
+pub const SCHEMA_VERSION: u32 = 1;
+// each migration leaves a copy of what it started from in here, e.g. backup/v0
+const BACKUP_DIR: &str = "backup";
+
+// a save folder in memory, paths relative to it mapped to the file's text
+type SaveFiles = BTreeMap<String, String>;
+
+// MIGRATIONS[v] takes a folder from version v to v + 1. Old steps are never
+// changed, they describe the files as they were at the time, so they spell
+// out their file names instead of using today's constants.
+const MIGRATIONS: [fn(&mut SaveFiles); SCHEMA_VERSION as usize] = [split_into_profiles];
+
+// 0 -> 1: the files that sat directly in the save folder move into the first
+// profile's folder, and the profile list is started with that profile
+fn split_into_profiles(files: &mut SaveFiles) {
+    for name in [
+        "settings.txt",
+        "highscores.txt",
+        "run_history.txt",
+        "achievements.txt",
+    ] {
+        if let Some(text) = files.remove(name) {
+            files.insert(format!("profiles/1/{}", name), text);
+        }
+    }
+    files.insert(
+        "profiles.txt".to_string(),
+        "profile=1 Player\ncurrent=1\n".to_string(),
+    );
+}
+
+// Brings the save folder up to SCHEMA_VERSION, call it before loading anything
+// from it. A folder from a newer version of the game is left alone.
+pub fn migrate(dir: &str) {
+    let mut files = SaveFiles::new();
+    read_folder(Path::new(dir), "", &mut files);
+    let version = version_of(&files);
+    if version > SCHEMA_VERSION {
+        eprintln!(
+            "{} is from a newer version of the game ({}), some of it may not load",
+            dir, version
+        );
+    }
+    if version >= SCHEMA_VERSION {
+        return;
+    }
+
+    let old = files.clone();
+    migrate_files(&mut files, version);
+    for (name, text) in &files {
+        if old.get(name) != Some(text) {
+            write_file(&format!("{}/{}", dir, name), text);
+        }
+    }
+    for name in old.keys().filter(|name| !files.contains_key(*name)) {
+        let path = format!("{}/{}", dir, name);
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("Failed to remove {}: {}", path, e);
+        }
+    }
+}
+
+fn migrate_files(files: &mut SaveFiles, from: u32) {
+    let backup: Vec<(String, String)> = files
+        .iter()
+        .map(|(name, text)| (format!("{}/v{}/{}", BACKUP_DIR, from, name), text.clone()))
+        .collect();
+    for step in &MIGRATIONS[from as usize..] {
+        step(files);
+    }
+    files.extend(backup);
+    files.insert(
+        VERSION_FILE.to_string(),
+        format!("version={}\n", SCHEMA_VERSION),
+    );
+}
+
+// version.txt arrived with version 1, before it there were no profiles either
+fn version_of(files: &SaveFiles) -> u32 {
+    let stamped = files.get(VERSION_FILE).and_then(|text| {
+        parse_key_values(text)
+            .find(|(key, _)| *key == "version")
+            .and_then(|(_, value)| value.parse().ok())
+    });
+    match stamped {
+        Some(version) => version,
+        None if files.contains_key("profiles.txt") => 1,
+        None => 0,
+    }
+}
+
+// every text file under `dir`, leaving out backups and unfinished writes
+fn read_folder(dir: &Path, prefix: &str, files: &mut SaveFiles) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let name = format!("{}{}", prefix, file_name);
+        let path = entry.path();
+        if path.is_dir() {
+            if name != BACKUP_DIR {
+                read_folder(&path, &format!("{}/", name), files);
+            }
+        } else if !name.ends_with(".tmp") && !name.ends_with(".bak") {
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    files.insert(name, text);
+                }
+                Err(e) => eprintln!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+// The text of a save file, or None if there isn't one. A file that isn't
+// text, or that `is_valid` turns down, is moved aside to `<path>.bak` and also
+// comes back as None, so the caller starts over from its defaults.
+pub fn read(path: &str, is_valid: impl Fn(&str) -> bool) -> Option<String> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return None;
+        }
+    };
+    match String::from_utf8(bytes) {
+        Ok(text) if is_valid(&text) => Some(text),
+        _ => {
+            let backup = format!("{}.bak", path);
+            eprintln!(
+                "{} is corrupt, using the defaults and keeping it as {}",
+                path, backup
+            );
+            if let Err(e) = fs::rename(path, &backup) {
+                eprintln!("Failed to move {} to {}: {}", path, backup, e);
+            }
+            None
+        }
+    }
+}
+
+// for `read`: every line is blank, a # comment or `key=value`
+pub fn is_key_values(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || line.starts_with('#') || line.contains('='))
+}
+
+fn create_parent(path: &str) -> bool {
+    if let Some(dir) = Path::new(path).parent()
+        && let Err(e) = fs::create_dir_all(dir)
+    {
+        eprintln!("Failed to create {}: {}", dir.display(), e);
+        return false;
+    }
+    true
+}
+
+// Written to `<path>.tmp` first, which then replaces the file, so a crash
+// halfway leaves the old file instead of half of the new one. Saving should
+// never take the game down, so failures are only reported.
+pub fn write_file(path: &str, text: &str) {
+    if !create_parent(path) {
+        return;
+    }
+    let tmp = format!("{}.tmp", path);
+    let result = fs::File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = result {
+        eprintln!("Failed to write {}: {}", path, e);
+    }
+}
+
+// adds `line` to the end of the file, creating it if needed; a crash halfway
+// can only cost that one line
+pub fn append_line(path: &str, line: &str) {
+    if !create_parent(path) {
+        return;
+    }
+    let result = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        eprintln!("Failed to write {}: {}", path, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highscores::{HIGHSCORES_FILE, HighScores};
+    use crate::profile::Profiles;
+    use crate::settings::{SETTINGS_FILE, Settings};
+
+    fn files(list: &[(&str, &str)]) -> SaveFiles {
+        list.iter()
+            .map(|(name, text)| (name.to_string(), text.to_string()))
+            .collect()
+    }
+
+    // a folder as version 0 of the game left it
+    fn version_0() -> SaveFiles {
+        files(&[
+            (
+                "settings.txt",
+                "difficulty=custom\nscreen_shake=0.5\nlanguage=de\ncustom.base_enemy_count=7\n",
+            ),
+            (
+                "highscores.txt",
+                "normal 1200 7\ncustom 300 2 base_enemy_count=7\n",
+            ),
+            ("run_history.txt", "difficulty=normal score=1200 wave=7\n"),
+            ("achievements.txt", "first_blood\n"),
+        ])
+    }
+
+    #[test]
+    fn the_version_comes_from_the_stamp_or_the_layout() {
+        assert_eq!(version_of(&version_0()), 0);
+        assert_eq!(
+            version_of(&files(&[("profiles.txt", "profile=1 Player")])),
+            1
+        );
+        assert_eq!(version_of(&files(&[(VERSION_FILE, "version=7")])), 7);
+    }
+
+    #[test]
+    fn version_0_moves_into_the_first_profile() {
+        let mut migrated = version_0();
+        migrate_files(&mut migrated, 0);
+        assert_eq!(version_of(&migrated), SCHEMA_VERSION);
+
+        // today's code finds the moved files where the profile says they are
+        let profiles = Profiles::parse(&migrated["profiles.txt"]);
+        let profile = profiles.current();
+        let in_folder = |file: &str| {
+            let path = profile.path(file);
+            migrated[path.strip_prefix("save/").unwrap()].clone()
+        };
+        assert_eq!(in_folder(SETTINGS_FILE), version_0()["settings.txt"]);
+        assert_eq!(in_folder(HIGHSCORES_FILE), version_0()["highscores.txt"]);
+        assert!(!migrated.contains_key("settings.txt"));
+    }
+
+    #[test]
+    fn version_0_survives_a_round_trip_through_today_s_loaders() {
+        let mut migrated = version_0();
+        migrate_files(&mut migrated, 0);
+
+        let settings = Settings::parse(&migrated["profiles/1/settings.txt"]);
+        assert_eq!(settings.language, "de");
+        assert_eq!(settings.screen_shake, 0.5);
+        let saved = settings.format();
+        assert_eq!(Settings::parse(&saved).format(), saved);
+
+        let scores = HighScores::parse(&migrated["profiles/1/highscores.txt"]);
+        let saved = scores.format();
+        assert!(saved.starts_with("normal 1200 7\ncustom 300 2 base_enemy_count=7 "));
+        assert_eq!(HighScores::parse(&saved).format(), saved);
+
+        let profiles = Profiles::parse(&migrated["profiles.txt"]);
+        assert_eq!(Profiles::parse(&profiles.format()), profiles);
+    }
+
+    #[test]
+    fn migrating_keeps_a_copy_of_the_old_files() {
+        let mut migrated = version_0();
+        migrate_files(&mut migrated, 0);
+        for (name, text) in version_0() {
+            assert_eq!(migrated[&format!("backup/v0/{}", name)], text);
+        }
+    }
+
+    #[test]
+    fn only_key_value_lines_are_valid() {
+        assert!(is_key_values("# comment\n\na = 1\nb=2"));
+        assert!(!is_key_values("a = 1\n\u{0}\u{0}garbage"));
+    }
+
+    #[test]
+    fn corrupt_files_are_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("save_test_{}", std::process::id()));
+        let path = dir.join("settings.txt").to_string_lossy().into_owned();
+        write_file(&path, "screen_shake=0.5\nnot a setting\n");
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+
+        assert_eq!(read(&path, is_key_values), None);
+        assert!(!Path::new(&path).exists());
+        assert_eq!(
+            fs::read_to_string(format!("{}.bak", path)).unwrap(),
+            "screen_shake=0.5\nnot a setting\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+use crate::difficulty::{Difficulty, DifficultyParams};
+use crate::locale::FALLBACK_LANGUAGE;
+use crate::save::{self, is_key_values, write_file};
+
+// kept in the profile's folder, see profile.rs
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+pub struct Settings {
+    pub difficulty: Difficulty,
+    // used when `difficulty` is Custom
+    pub custom: DifficultyParams,
+    pub show_health_bars: bool,
+    // 0.0 turns screen shake off, 1.0 is full strength
+    pub screen_shake: f32,
+    pub hit_stop: bool,
+    pub fullscreen: bool,
+    // scale the playfield by whole numbers only, leaving wider black bars
+    pub integer_scaling: bool,
+    // a locale file name in assets/locale, e.g. "en"
+    pub language: String,
+    // every sound from the middle instead of panned to where it happened
+    pub mono_audio: bool,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings {
+            difficulty: Difficulty::Normal,
+            custom: DifficultyParams::NORMAL,
+            show_health_bars: true,
+            screen_shake: 1.0,
+            hit_stop: true,
+            fullscreen: false,
+            integer_scaling: false,
+            language: FALLBACK_LANGUAGE.to_string(),
+            mono_audio: false,
+        }
+    }
+
+    // a missing or corrupt file means all the defaults, see save::read
+    pub fn load(path: &str) -> Self {
+        Settings::parse(&save::read(path, is_key_values).unwrap_or_default())
+    }
+
+    // anything the text leaves out, or has a bad value for, is the default
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Settings::new();
+        for (key, value) in parse_key_values(text) {
+            if key == "difficulty" {
+                if let Some(difficulty) = Difficulty::from_name(value) {
+                    settings.difficulty = difficulty;
+                }
+            } else if key == "show_health_bars" {
+                settings.show_health_bars = value == "true";
+            } else if key == "screen_shake" {
+                // "NaN" parses and would get through the clamp
+                if let Ok(shake) = value.parse::<f32>()
+                    && shake.is_finite()
+                {
+                    settings.screen_shake = shake.clamp(0.0, 1.0);
+                }
+            } else if key == "hit_stop" {
+                settings.hit_stop = value == "true";
+            } else if key == "fullscreen" {
+                settings.fullscreen = value == "true";
+            } else if key == "integer_scaling" {
+                settings.integer_scaling = value == "true";
+            } else if key == "language" {
+                settings.language = value.to_string();
+            } else if key == "mono_audio" {
+                settings.mono_audio = value == "true";
+            } else if let Some(field) = key.strip_prefix("custom.") {
+                settings.custom.set_field(field, value);
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self, path: &str) {
+        write_file(path, &self.format());
+    }
+
+    pub fn format(&self) -> String {
+        let mut text = format!("difficulty={}\n", self.difficulty.name());
+        text.push_str(&format!("show_health_bars={}\n", self.show_health_bars));
+        text.push_str(&format!("screen_shake={}\n", self.screen_shake));
+        text.push_str(&format!("hit_stop={}\n", self.hit_stop));
+        text.push_str(&format!("fullscreen={}\n", self.fullscreen));
+        text.push_str(&format!("integer_scaling={}\n", self.integer_scaling));
+        text.push_str(&format!("language={}\n", self.language));
+        text.push_str(&format!("mono_audio={}\n", self.mono_audio));
+        for (key, value) in self.custom.fields() {
+            text.push_str(&format!("custom.{}={}\n", key, value));
+        }
+        text
+    }
+}
+
+// `key=value` per line, blank lines and lines starting with # are skipped
+pub fn parse_key_values(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_shake_is_kept_between_off_and_full() {
+        assert_eq!(Settings::parse("screen_shake=0.25").screen_shake, 0.25);
+        assert_eq!(Settings::parse("screen_shake=4").screen_shake, 1.0);
+        for bad in ["NaN", "inf", "-inf", "lots"] {
+            let settings = Settings::parse(&format!("screen_shake={}", bad));
+            assert_eq!(settings.screen_shake, Settings::new().screen_shake);
+        }
+    }
+}
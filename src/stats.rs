@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use crate::difficulty::Difficulty;
+use crate::enemy::EnemyType;
+use crate::events::{GameEvent, Subscriber};
+use crate::locale::Strings;
+use crate::save::append_line;
+
+// one line per finished run, never trimmed, for looking at later; kept in the
+// profile's folder, see profile.rs
+pub const RUN_HISTORY_FILE: &str = "run_history.txt";
+
+// What happened during one run, gathered from the gameplay events
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct RunStats {
+    pub shots_fired: u32,
+    // shots that hit something, going on through more enemies doesn't add to it
+    pub hits: u32,
+    pub kills: HashMap<EnemyType, u32>,
+    // enemies that got past the bottom of the screen
+    pub breaches: u32,
+    // seconds actually played, pauses and hit-stop don't count
+    pub time_alive: f32,
+    // counted from 1 like the wave banner
+    pub highest_wave: u32,
+    pub peak_combo: i32,
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        RunStats {
+            highest_wave: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time_alive += dt;
+    }
+
+    pub fn kills_of(&self, enemy_type: EnemyType) -> u32 {
+        self.kills.get(&enemy_type).copied().unwrap_or(0)
+    }
+
+    // share of the shots that hit, as 0..1
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+        self.hits as f32 / self.shots_fired as f32
+    }
+
+    // `key=value` pairs on one line, like the custom difficulty in highscores.txt
+    pub fn history_line(&self, difficulty: Difficulty, score: i32) -> String {
+        let mut line = format!(
+            "difficulty={} score={} wave={} time={:.1} shots={} hits={} breaches={} peak_combo={}",
+            difficulty.name(),
+            score,
+            self.highest_wave,
+            self.time_alive,
+            self.shots_fired,
+            self.hits,
+            self.breaches,
+            self.peak_combo,
+        );
+        for enemy_type in EnemyType::ALL {
+            line.push_str(&format!(
+                " kills.{}={}",
+                enemy_type.name(),
+                self.kills_of(enemy_type)
+            ));
+        }
+        line
+    }
+
+    pub fn append_to_history(&self, path: &str, difficulty: Difficulty, score: i32) {
+        append_line(path, &self.history_line(difficulty, score));
+    }
+}
+
+impl Subscriber for RunStats {
+    fn on_event(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::ShotFired { .. } => self.shots_fired += 1,
+            GameEvent::EnemyHit { first_hit, .. } => {
+                if first_hit {
+                    self.hits += 1;
+                }
+            }
+            GameEvent::EnemyKilled {
+                enemy_type, chain, ..
+            } => {
+                *self.kills.entry(enemy_type).or_insert(0) += 1;
+                self.peak_combo = self.peak_combo.max(chain);
+            }
+            GameEvent::EnemyBreached { .. } => self.breaches += 1,
+            // the cleared wave counts from 0, and the next one has begun
+            GameEvent::WaveCleared { wave_number } => {
+                self.highest_wave = self.highest_wave.max(wave_number + 2);
+            }
+            GameEvent::ShotMissed
+            | GameEvent::BatteryEmpty { .. }
+            | GameEvent::PlayerHit { .. } => {}
+        }
+    }
+}
+
+// what the summary panel on the game over screen shows, as (label, value)
+pub fn summary_rows(stats: &RunStats, strings: &Strings) -> Vec<(String, String)> {
+    let row = |key: &str, value: String| (strings.get(key).to_string(), value);
+    vec![
+        row(
+            "summary.accuracy",
+            format!("{}%", (stats.accuracy() * 100.0).round()),
+        ),
+        row("summary.shots", stats.shots_fired.to_string()),
+        row("summary.hits", stats.hits.to_string()),
+        row("summary.time", format_time(stats.time_alive)),
+        row("summary.wave", stats.highest_wave.to_string()),
+        row("summary.peak_combo", stats.peak_combo.to_string()),
+        row("summary.breaches", stats.breaches.to_string()),
+        row(
+            "summary.kills_default",
+            stats.kills_of(EnemyType::Default).to_string(),
+        ),
+        row(
+            "summary.kills_fast",
+            stats.kills_of(EnemyType::Fast).to_string(),
+        ),
+        row(
+            "summary.kills_armored",
+            stats.kills_of(EnemyType::Armored).to_string(),
+        ),
+    ]
+}
+
+// "1:05" for 65 seconds
+pub fn format_time(seconds: f32) -> String {
+    let seconds = seconds.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sfml::system::Vector2f;
+
+    const HERE: Vector2f = Vector2f::new(0.0, 0.0);
+
+    fn hit(first_hit: bool) -> GameEvent {
+        GameEvent::EnemyHit {
+            enemy_type: EnemyType::Fast,
+            pos: HERE,
+            angle: 0.0,
+            first_hit,
+        }
+    }
+
+    fn kill(enemy_type: EnemyType, chain: i32) -> GameEvent {
+        GameEvent::EnemyKilled {
+            enemy_type,
+            pos: HERE,
+            angle: 0.0,
+            points: 100,
+            chain,
+        }
+    }
+
+    #[test]
+    fn events_add_up() {
+        let mut stats = RunStats::new();
+        stats.on_event(&GameEvent::ShotFired { pos: HERE });
+        stats.on_event(&GameEvent::ShotFired { pos: HERE });
+        stats.on_event(&hit(true));
+        stats.on_event(&kill(EnemyType::Fast, 3));
+        stats.on_event(&kill(EnemyType::Fast, 1));
+        stats.on_event(&GameEvent::EnemyBreached {
+            enemy_type: EnemyType::Armored,
+            pos: HERE,
+        });
+        stats.on_event(&GameEvent::WaveCleared { wave_number: 0 });
+
+        assert_eq!(stats.shots_fired, 2);
+        assert_eq!(stats.accuracy(), 0.5);
+        assert_eq!(stats.kills_of(EnemyType::Fast), 2);
+        assert_eq!(stats.kills_of(EnemyType::Armored), 0);
+        assert_eq!(stats.peak_combo, 3);
+        assert_eq!(stats.breaches, 1);
+        assert_eq!(stats.highest_wave, 2);
+    }
+
+    #[test]
+    fn a_shot_through_several_enemies_is_one_hit() {
+        let mut stats = RunStats::new();
+        stats.on_event(&GameEvent::ShotFired { pos: HERE });
+        stats.on_event(&hit(true));
+        stats.on_event(&hit(false));
+        stats.on_event(&hit(false));
+        stats.on_event(&GameEvent::ShotFired { pos: HERE });
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.accuracy(), 0.5);
+    }
+
+    #[test]
+    fn accuracy_is_zero_without_shots() {
+        assert_eq!(RunStats::new().accuracy(), 0.0);
+    }
+
+    #[test]
+    fn history_line_has_every_kill_type() {
+        let mut stats = RunStats::new();
+        stats.on_event(&kill(EnemyType::Armored, 1));
+        stats.time_alive = 12.34;
+        let line = stats.history_line(Difficulty::Hard, 400);
+        assert!(line.starts_with("difficulty=hard score=400 wave=1 time=12.3 "));
+        assert!(line.ends_with("kills.default=0 kills.fast=0 kills.armored=1"));
+    }
+
+    #[test]
+    fn time_reads_as_minutes_and_seconds() {
+        assert_eq!(format_time(65.9), "1:05");
+        assert_eq!(format_time(0.0), "0:00");
+    }
+}
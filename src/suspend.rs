@@ -0,0 +1,427 @@
+use std::fs;
+use std::io::ErrorKind;
+
+use sfml::system::Vector2f;
+
+use crate::achievements::RunProgress;
+use crate::combo::{COMBO_WINDOW, Combo, MAX_MULTIPLIER};
+use crate::difficulty::{Difficulty, DifficultyParams};
+use crate::enemy::EnemyType;
+use crate::projectile::{MAX_HITS, Projectile};
+use crate::save::{self, is_key_values, write_file};
+use crate::settings::parse_key_values;
+use crate::stats::RunStats;
+
+// kept in the profile's folder, see profile.rs
+pub const SUSPENDED_RUN_FILE: &str = "suspended_run.txt";
+
+// an enemy's speed and full health come from its type and the difficulty
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SuspendedEnemy {
+    pub enemy_type: EnemyType,
+    // world position, see iso.rs
+    pub world: Vector2f,
+    pub health: i32,
+}
+
+// A run as it was when the window closed, to be offered as Continue next
+// time. Effects, particles and the camera aren't kept, they settle in a moment.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SuspendedRun {
+    pub difficulty: Difficulty,
+    pub params: DifficultyParams,
+    // the waves still to come are spawned from it, see enemy::spawn_wave
+    pub seed: u64,
+    pub score: i32,
+    pub wave_number: u32,
+    pub ship: Vector2f,
+    pub battery: i32,
+    pub reload_time: f32,
+    pub enemies: Vec<SuspendedEnemy>,
+    pub projectiles: Vec<Projectile>,
+    // so the summary at the end covers the whole run, not just the part after continuing
+    pub stats: RunStats,
+    pub combo: Combo,
+    pub achievements: RunProgress,
+}
+
+impl SuspendedRun {
+    // None if there's no run to continue, or it can't be made sense of
+    pub fn load(path: &str) -> Option<Self> {
+        SuspendedRun::parse(&save::read(path, is_key_values)?)
+    }
+
+    pub fn save(&self, path: &str) {
+        write_file(path, &self.format());
+    }
+
+    // a run can only be continued once
+    pub fn discard(path: &str) {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                eprintln!("Failed to remove {}: {}", path, e)
+            }
+            _ => {}
+        }
+    }
+
+    // `key=value` lines, vectors as `x y`, and one `enemy` or `projectile` line each;
+    // the stats and combo come last, as `stats.` and `combo.` keys. The file may
+    // have been edited by hand, so a number that isn't finite means there's no
+    // run, values out of range are clamped and dead enemies and projectiles that
+    // couldn't have been fired are left out.
+    fn parse(text: &str) -> Option<Self> {
+        let mut difficulty = None;
+        let mut params = DifficultyParams::NORMAL;
+        let mut seed = None;
+        let mut score = None;
+        let mut wave_number = None;
+        let mut ship = None;
+        let mut battery: Option<i32> = None;
+        let mut reload_time = None;
+        let mut enemies = Vec::new();
+        let mut projectiles = Vec::new();
+        let mut stats = RunStats::new();
+        let mut combo = Combo::new();
+        let mut achievements = RunProgress::default();
+
+        for (key, value) in parse_key_values(text) {
+            match key {
+                "difficulty" => difficulty = Difficulty::from_name(value),
+                "seed" => seed = value.parse().ok(),
+                "score" => score = value.parse().ok(),
+                "wave" => wave_number = value.parse().ok(),
+                "ship" => ship = parse_vector(&mut value.split_whitespace()),
+                "battery" => battery = value.parse().ok(),
+                "reload_time" => reload_time = parse_finite(value).map(|t| t.max(0.0)),
+                "enemy" => enemies.push(parse_enemy(value)?),
+                "projectile" => projectiles.push(parse_projectile(value)?),
+                _ => {
+                    if let Some(field) = key.strip_prefix("params.") {
+                        params.set_field(field, value);
+                    } else if let Some(field) = key.strip_prefix("stats.") {
+                        set_stat(&mut stats, field, value)?;
+                    } else if let Some(field) = key.strip_prefix("combo.") {
+                        set_combo(&mut combo, field, value)?;
+                    } else if let Some(field) = key.strip_prefix("achievements.") {
+                        set_achievement(&mut achievements, field, value)?;
+                    }
+                }
+            }
+        }
+        let wave_number = wave_number?;
+        // runs saved before the stats were kept still know which wave they got to
+        stats.highest_wave = stats.highest_wave.max(wave_number + 1);
+        enemies.retain(|e| e.health > 0);
+        projectiles.retain(|p| {
+            p.direction.abs() == 1 && p.speed > 0.0 && (0..MAX_HITS).contains(&p.damage)
+        });
+        combo.multiplier = combo.multiplier.clamp(1, MAX_MULTIPLIER);
+        combo.chain = combo.chain.max(0);
+
+        Some(SuspendedRun {
+            difficulty: difficulty?,
+            params,
+            seed: seed?,
+            score: score?,
+            wave_number,
+            ship: ship?,
+            battery: battery?.clamp(0, params.battery_size),
+            reload_time: reload_time?,
+            enemies,
+            projectiles,
+            stats,
+            combo,
+            achievements,
+        })
+    }
+
+    fn format(&self) -> String {
+        let mut text = format!("difficulty={}\n", self.difficulty.name());
+        for (key, value) in self.params.fields() {
+            text.push_str(&format!("params.{}={}\n", key, value));
+        }
+        text.push_str(&format!("seed={}\n", self.seed));
+        text.push_str(&format!("score={}\n", self.score));
+        text.push_str(&format!("wave={}\n", self.wave_number));
+        text.push_str(&format!("ship={} {}\n", self.ship.x, self.ship.y));
+        text.push_str(&format!("battery={}\n", self.battery));
+        text.push_str(&format!("reload_time={}\n", self.reload_time));
+        for enemy in &self.enemies {
+            text.push_str(&format!(
+                "enemy={} {} {} {}\n",
+                enemy.enemy_type.name(),
+                enemy.world.x,
+                enemy.world.y,
+                enemy.health
+            ));
+        }
+        for projectile in &self.projectiles {
+            text.push_str(&format!(
+                "projectile={} {} {} {} {} {}\n",
+                projectile.position.x,
+                projectile.position.y,
+                projectile.speed,
+                projectile.direction,
+                projectile.damage,
+                projectile.kills
+            ));
+        }
+        let stats = &self.stats;
+        text.push_str(&format!("stats.shots={}\n", stats.shots_fired));
+        text.push_str(&format!("stats.hits={}\n", stats.hits));
+        for enemy_type in EnemyType::ALL
+            .into_iter()
+            .filter(|t| stats.kills_of(*t) > 0)
+        {
+            text.push_str(&format!(
+                "stats.kills.{}={}\n",
+                enemy_type.name(),
+                stats.kills_of(enemy_type)
+            ));
+        }
+        text.push_str(&format!("stats.breaches={}\n", stats.breaches));
+        text.push_str(&format!("stats.time={}\n", stats.time_alive));
+        text.push_str(&format!("stats.highest_wave={}\n", stats.highest_wave));
+        text.push_str(&format!("stats.peak_combo={}\n", stats.peak_combo));
+        text.push_str(&format!("combo.chain={}\n", self.combo.chain));
+        text.push_str(&format!("combo.multiplier={}\n", self.combo.multiplier));
+        text.push_str(&format!("combo.timer={}\n", self.combo.timer));
+        text.push_str(&format!(
+            "achievements.fired_this_wave={}\n",
+            self.achievements.fired_this_wave
+        ));
+        for (id, count) in &self.achievements.counters {
+            text.push_str(&format!("achievements.progress.{}={}\n", id, count));
+        }
+        text
+    }
+}
+
+// None if the value doesn't parse; unknown stats are left alone
+fn set_stat(stats: &mut RunStats, field: &str, value: &str) -> Option<()> {
+    match field {
+        "shots" => stats.shots_fired = value.parse().ok()?,
+        "hits" => stats.hits = value.parse().ok()?,
+        "breaches" => stats.breaches = value.parse().ok()?,
+        "time" => stats.time_alive = parse_finite(value)?.max(0.0),
+        "highest_wave" => stats.highest_wave = value.parse().ok()?,
+        "peak_combo" => stats.peak_combo = value.parse().ok()?,
+        _ => {
+            if let Some(enemy_type) = field.strip_prefix("kills.").and_then(EnemyType::from_name) {
+                stats.kills.insert(enemy_type, value.parse().ok()?);
+            }
+        }
+    }
+    Some(())
+}
+
+fn set_achievement(achievements: &mut RunProgress, field: &str, value: &str) -> Option<()> {
+    if field == "fired_this_wave" {
+        achievements.fired_this_wave = value.parse().ok()?;
+    } else if let Some(id) = field.strip_prefix("progress.") {
+        achievements
+            .counters
+            .push((id.to_string(), value.parse().ok()?));
+    }
+    Some(())
+}
+
+fn set_combo(combo: &mut Combo, field: &str, value: &str) -> Option<()> {
+    match field {
+        "chain" => combo.chain = value.parse().ok()?,
+        "multiplier" => combo.multiplier = value.parse().ok()?,
+        "timer" => combo.timer = parse_finite(value)?.clamp(0.0, COMBO_WINDOW),
+        _ => {}
+    }
+    Some(())
+}
+
+// "NaN" and "inf" parse as f32 too, but would spread through everything they touch
+fn parse_finite(value: &str) -> Option<f32> {
+    value.parse().ok().filter(|v: &f32| v.is_finite())
+}
+
+fn parse_vector<'t>(parts: &mut impl Iterator<Item = &'t str>) -> Option<Vector2f> {
+    let x = parse_finite(parts.next()?)?;
+    let y = parse_finite(parts.next()?)?;
+    Some(Vector2f::new(x, y))
+}
+
+// `type x y health`
+fn parse_enemy(value: &str) -> Option<SuspendedEnemy> {
+    let mut parts = value.split_whitespace();
+    Some(SuspendedEnemy {
+        enemy_type: EnemyType::from_name(parts.next()?)?,
+        world: parse_vector(&mut parts)?,
+        health: parts.next()?.parse().ok()?,
+    })
+}
+
+// `x y speed direction damage kills`
+fn parse_projectile(value: &str) -> Option<Projectile> {
+    let mut parts = value.split_whitespace();
+    Some(Projectile {
+        position: parse_vector(&mut parts)?,
+        speed: parse_finite(parts.next()?)?,
+        direction: parts.next()?.parse().ok()?,
+        damage: parts.next()?.parse().ok()?,
+        kills: parts.next()?.parse().ok()?,
+        alive: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run() -> SuspendedRun {
+        let mut params = Difficulty::Hard.params(&DifficultyParams::NORMAL);
+        params.speed_scale = 1.35;
+        SuspendedRun {
+            difficulty: Difficulty::Hard,
+            params,
+            seed: 0xDEAD_BEEF_1234,
+            score: 4150,
+            wave_number: 6,
+            ship: Vector2f::new(-12.5, 40.25),
+            battery: 3,
+            reload_time: 0.37,
+            enemies: vec![
+                SuspendedEnemy {
+                    enemy_type: EnemyType::Armored,
+                    world: Vector2f::new(310.1, 122.7),
+                    health: 2,
+                },
+                SuspendedEnemy {
+                    enemy_type: EnemyType::Fast,
+                    world: Vector2f::new(-80.0, 5.5),
+                    health: 1,
+                },
+            ],
+            projectiles: vec![Projectile {
+                position: Vector2f::new(20.0, 333.3),
+                speed: 8.0,
+                direction: 1,
+                damage: 1,
+                kills: 1,
+                alive: true,
+            }],
+            stats: RunStats {
+                shots_fired: 40,
+                hits: 31,
+                kills: [(EnemyType::Default, 18), (EnemyType::Armored, 4)].into(),
+                breaches: 2,
+                time_alive: 173.25,
+                highest_wave: 7,
+                peak_combo: 11,
+            },
+            combo: Combo {
+                chain: 7,
+                multiplier: 3,
+                timer: 1.4,
+            },
+            achievements: RunProgress {
+                counters: vec![
+                    ("exterminator".to_string(), 22),
+                    ("flawless".to_string(), 5),
+                ],
+                fired_this_wave: true,
+            },
+        }
+    }
+
+    #[test]
+    fn a_run_survives_a_round_trip() {
+        assert_eq!(SuspendedRun::parse(&run().format()), Some(run()));
+    }
+
+    #[test]
+    fn a_run_saved_without_stats_starts_them_at_its_wave() {
+        let old: String = run()
+            .format()
+            .lines()
+            .filter(|line| {
+                !["stats.", "combo.", "achievements."]
+                    .iter()
+                    .any(|prefix| line.starts_with(prefix))
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let resumed = SuspendedRun::parse(&old).unwrap();
+        assert_eq!(resumed.stats.highest_wave, 7);
+        assert_eq!(resumed.stats.shots_fired, 0);
+        assert_eq!(resumed.combo, Combo::new());
+        assert_eq!(resumed.achievements, RunProgress::default());
+    }
+
+    #[test]
+    fn an_empty_wave_is_still_a_run() {
+        let mut empty = run();
+        empty.enemies.clear();
+        empty.projectiles.clear();
+        assert_eq!(SuspendedRun::parse(&empty.format()), Some(empty));
+    }
+
+    #[test]
+    fn missing_or_broken_parts_mean_nothing_to_continue() {
+        let text = run().format();
+        let without_seed: String = text
+            .lines()
+            .filter(|line| !line.starts_with("seed="))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(SuspendedRun::parse(&without_seed), None);
+        let broken_enemy = format!("{}enemy=dragon 1 2 3\n", text);
+        assert_eq!(SuspendedRun::parse(&broken_enemy), None);
+    }
+
+    fn with_line(run: &SuspendedRun, line: &str) -> Option<SuspendedRun> {
+        SuspendedRun::parse(&format!("{}{}\n", run.format(), line))
+    }
+
+    #[test]
+    fn numbers_that_are_not_finite_mean_nothing_to_continue() {
+        for line in [
+            "ship=NaN 4",
+            "enemy=fast inf 2 1",
+            "projectile=1 2 NaN 1 0 0",
+            "reload_time=-inf",
+            "stats.time=NaN",
+            "combo.timer=inf",
+        ] {
+            assert_eq!(with_line(&run(), line), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn dead_enemies_are_left_out() {
+        let resumed = with_line(&run(), "enemy=default 1 2 0").unwrap();
+        assert_eq!(resumed.enemies, run().enemies);
+        let resumed = with_line(&run(), "enemy=default 1 2 -3").unwrap();
+        assert_eq!(resumed.enemies, run().enemies);
+    }
+
+    #[test]
+    fn projectiles_that_could_not_have_been_fired_are_left_out() {
+        for line in [
+            "projectile=1 2 8 3 0 0",
+            "projectile=1 2 8 0 0 0",
+            "projectile=1 2 -8 1 0 0",
+            "projectile=1 2 8 1 2 0",
+        ] {
+            let resumed = with_line(&run(), line).unwrap();
+            assert_eq!(resumed.projectiles, run().projectiles, "{}", line);
+        }
+    }
+
+    #[test]
+    fn the_battery_and_multiplier_are_clamped() {
+        let resumed = with_line(&run(), "battery=99\ncombo.multiplier=40").unwrap();
+        assert_eq!(resumed.battery, run().params.battery_size);
+        assert_eq!(resumed.combo.multiplier, MAX_MULTIPLIER);
+        let resumed = with_line(&run(), "battery=-2\ncombo.multiplier=0").unwrap();
+        assert_eq!(resumed.battery, 0);
+        assert_eq!(resumed.combo.multiplier, 1);
+    }
+}
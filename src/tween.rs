@@ -0,0 +1,258 @@
+use sfml::graphics::Color;
+use sfml::system::Vector2f;
+
+// Easing curves, each maps 0..1 progress to 0..1 (OutBack overshoots a little
+// on the way)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ease {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    OutCubic,
+    OutBack,
+}
+
+impl Ease {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Ease::Linear => t,
+            Ease::InQuad => t * t,
+            Ease::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::InOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Ease::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Ease::OutBack => {
+                const OVERSHOOT: f32 = 1.70158;
+                let u = t - 1.0;
+                1.0 + (OVERSHOOT + 1.0) * u.powi(3) + OVERSHOOT * u.powi(2)
+            }
+        }
+    }
+}
+
+// Anything a tween can move between
+pub trait Lerp: Copy {
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vector2f {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+// channels are clamped, so overshooting curves can't wrap a colour around
+impl Lerp for Color {
+    fn lerp(self, to: Self, t: f32) -> Self {
+        let channel = |a: u8, b: u8| (a as f32).lerp(b as f32, t).round().clamp(0.0, 255.0) as u8;
+        Color::rgba(
+            channel(self.r, to.r),
+            channel(self.g, to.g),
+            channel(self.b, to.b),
+            channel(self.a, to.a),
+        )
+    }
+}
+
+// Moves a value from `from` to `to` over `duration` seconds along an easing
+// curve, optionally after waiting `delay` seconds
+#[derive(Clone, Copy)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    duration: f32,
+    delay: f32,
+    time: f32,
+    ease: Ease,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, ease: Ease) -> Self {
+        Tween {
+            from,
+            to,
+            duration,
+            delay: 0.0,
+            time: 0.0,
+            ease,
+        }
+    }
+
+    // a tween that already sits at `value`
+    pub fn at(value: T) -> Self {
+        Tween::new(value, value, 0.0, Ease::Linear)
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn progress(&self) -> f32 {
+        let time = self.time - self.delay;
+        if self.duration <= 0.0 {
+            return if time >= 0.0 { 1.0 } else { 0.0 };
+        }
+        (time / self.duration).clamp(0.0, 1.0)
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(self.to, self.ease.apply(self.progress()))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.delay + self.duration
+    }
+
+    // heads for a new target from wherever the value is now, so changing
+    // direction halfway never makes it jump
+    pub fn retarget(&mut self, to: T, duration: f32, ease: Ease) {
+        *self = Tween::new(self.value(), to, duration, ease);
+    }
+}
+
+// Fades to black, swaps to the next screen while the picture is hidden and
+// fades back in. `S` is whatever identifies a screen.
+pub struct Transition<S: Copy> {
+    next: Option<S>,
+    alpha: Tween<f32>,
+    duration: f32,
+}
+
+impl<S: Copy> Transition<S> {
+    pub fn new(duration: f32) -> Self {
+        Transition {
+            next: None,
+            // the game opens with a fade in
+            alpha: Tween::new(255.0, 0.0, duration, Ease::OutQuad),
+            duration,
+        }
+    }
+
+    // ignored while another change is on its way, so a double click can't skip a screen
+    pub fn go_to(&mut self, screen: S) {
+        if self.next.is_none() {
+            self.next = Some(screen);
+            self.alpha.retarget(255.0, self.duration, Ease::InQuad);
+        }
+    }
+
+    // true from go_to until the screen has been swapped
+    pub fn is_leaving(&self) -> bool {
+        self.next.is_some()
+    }
+
+    // the screen being faded to, if any
+    pub fn heading_to(&self) -> Option<S> {
+        self.next
+    }
+
+    // returns the screen to switch to once the fade out has finished
+    pub fn update(&mut self, dt: f32) -> Option<S> {
+        self.alpha.update(dt);
+        if self.alpha.is_finished()
+            && let Some(next) = self.next.take()
+        {
+            self.alpha = Tween::new(255.0, 0.0, self.duration, Ease::OutQuad);
+            return Some(next);
+        }
+        None
+    }
+
+    // opacity of the black overlay drawn over everything
+    pub fn overlay_alpha(&self) -> u8 {
+        self.alpha.value().clamp(0.0, 255.0) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Ease; 6] = [
+        Ease::Linear,
+        Ease::InQuad,
+        Ease::OutQuad,
+        Ease::InOutQuad,
+        Ease::OutCubic,
+        Ease::OutBack,
+    ];
+
+    #[test]
+    fn every_curve_starts_at_zero_and_ends_at_one() {
+        for ease in ALL {
+            assert!(ease.apply(0.0).abs() < 1e-5, "{:?} at 0", ease);
+            assert!((ease.apply(1.0) - 1.0).abs() < 1e-5, "{:?} at 1", ease);
+        }
+    }
+
+    #[test]
+    fn tween_reaches_its_target_and_stays_there() {
+        let mut tween = Tween::new(1.0, 1.5, 0.2, Ease::OutQuad);
+        tween.update(0.1);
+        assert!(tween.value() > 1.0 && tween.value() < 1.5);
+        assert!(!tween.is_finished());
+        tween.update(0.5);
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 1.5);
+    }
+
+    #[test]
+    fn delay_holds_the_start_value() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0, Ease::Linear).with_delay(0.5);
+        tween.update(0.4);
+        assert_eq!(tween.value(), 0.0);
+        tween.update(0.6);
+        assert!((tween.value() - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn retarget_continues_from_the_current_value() {
+        let mut tween = Tween::new(0.0, 100.0, 1.0, Ease::Linear);
+        tween.update(0.25);
+        tween.retarget(0.0, 1.0, Ease::Linear);
+        assert!((tween.value() - 25.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn colour_channels_never_wrap_on_overshoot() {
+        let white = Color::rgb(250, 250, 250);
+        let mid = Color::BLACK.lerp(white, 1.1);
+        assert_eq!(mid.r, 255);
+    }
+
+    #[test]
+    fn transition_swaps_only_once_the_screen_is_black() {
+        let mut transition = Transition::new(0.2);
+        transition.update(1.0);
+        assert_eq!(transition.overlay_alpha(), 0);
+
+        transition.go_to("game");
+        transition.go_to("settings");
+        assert_eq!(transition.update(0.1), None);
+        assert!(transition.is_leaving());
+        assert_eq!(transition.update(0.15), Some("game"));
+        assert_eq!(transition.overlay_alpha(), 255);
+        assert!(!transition.is_leaving());
+
+        transition.update(0.3);
+        assert_eq!(transition.overlay_alpha(), 0);
+    }
+}
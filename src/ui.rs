@@ -0,0 +1,916 @@
+use sfml::graphics::{
+    Color, FloatRect, Font, RectangleShape, RenderTarget, RenderWindow, Shape, Sprite, Text,
+    Texture, Transformable,
+};
+use sfml::system::{Vector2f, Vector2i};
+use sfml::window::{Event, mouse};
+
+use crate::input::Action;
+use crate::tween::{Ease, Tween};
+
+const SLIDER_WIDTH: f32 = 200.0;
+const SLIDER_HEIGHT: f32 = 6.0;
+const KNOB_SIZE: Vector2f = Vector2f::new(14.0, 26.0);
+const TOGGLE_SIZE: f32 = 28.0;
+const TAB_SIZE: Vector2f = Vector2f::new(64.0, 40.0);
+const TAB_SPACING: f32 = 84.0;
+const PIP_SIZE: f32 = 8.0;
+const FOCUS_COLOR: Color = Color::rgb(255, 210, 60);
+const FILL_COLOR: Color = Color::rgb(90, 200, 90);
+// how far one left or right press moves a slider
+const SLIDER_STEP: f32 = 0.1;
+// time to grow into or shrink out of the hover scale
+const HOVER_TIME: f32 = 0.12;
+// gap between a focused button and the frame drawn around it
+const FOCUS_PADDING: f32 = 6.0;
+// hit area of a choice, wide enough for the longest option
+const CHOICE_SIZE: Vector2f = Vector2f::new(240.0, 36.0);
+const CHOICE_TEXT_SIZE: u32 = 24;
+
+// What a label or button shows: a picture, or text in the menu font
+enum Graphic<'a> {
+    Picture {
+        sprite: Sprite<'a>,
+        texture: &'a Texture,
+        hover_texture: &'a Texture,
+    },
+    // `color` is the colour when not highlighted
+    Text {
+        text: Text<'a>,
+        color: Color,
+    },
+}
+
+impl<'a> Graphic<'a> {
+    fn picture(texture: &'a Texture, hover_texture: &'a Texture, position: Vector2f) -> Self {
+        let mut sprite = Sprite::with_texture(texture);
+        let size = texture.size();
+        sprite.set_origin(Vector2f::new(size.x as f32 / 2.0, size.y as f32 / 2.0));
+        sprite.set_position(position);
+        Graphic::Picture {
+            sprite,
+            texture,
+            hover_texture,
+        }
+    }
+
+    fn text(string: &str, font: &'a Font, size: u32, position: Vector2f) -> Self {
+        let mut text = Text::new(string, font, size);
+        center_origin(&mut text);
+        text.set_position(position);
+        Graphic::Text {
+            text,
+            color: Color::WHITE,
+        }
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        match self {
+            Graphic::Picture { sprite, .. } => sprite.set_scale(scale),
+            Graphic::Text { text, .. } => text.set_scale(scale),
+        }
+    }
+
+    fn global_bounds(&self) -> FloatRect {
+        match self {
+            Graphic::Picture { sprite, .. } => sprite.global_bounds(),
+            Graphic::Text { text, .. } => text.global_bounds(),
+        }
+    }
+
+    // highlighted pictures swap to their hover texture, text turns gold
+    fn draw(&mut self, window: &mut RenderWindow, highlighted: bool) {
+        match self {
+            Graphic::Picture {
+                sprite,
+                texture,
+                hover_texture,
+            } => {
+                sprite.set_texture(if highlighted { hover_texture } else { texture }, false);
+                window.draw(sprite);
+            }
+            Graphic::Text { text, color } => {
+                text.set_fill_color(if highlighted { FOCUS_COLOR } else { *color });
+                window.draw(text);
+            }
+        }
+    }
+}
+
+// text is positioned by its middle, like everything else in a menu
+fn center_origin(text: &mut Text) {
+    let bounds = text.local_bounds();
+    text.set_origin(Vector2f::new(
+        bounds.left + bounds.width / 2.0,
+        bounds.top + bounds.height / 2.0,
+    ));
+}
+
+// One entry of a list widget: a coloured tab with one to three pips or an icon
+pub struct ListItem<'a> {
+    pub color: Color,
+    pub pips: usize,
+    pub icon: Option<&'a Texture>,
+}
+
+enum Kind<'a> {
+    // only grows when hovered, like the game title
+    Label {
+        graphic: Graphic<'a>,
+        scale: f32,
+        hover_scale: f32,
+        current: Tween<f32>,
+    },
+    Button {
+        graphic: Graphic<'a>,
+        scale: f32,
+        hover_scale: f32,
+        current: Tween<f32>,
+    },
+    // value goes from 0.0 to 1.0
+    Slider {
+        value: f32,
+    },
+    Toggle {
+        on: bool,
+    },
+    List {
+        items: Vec<ListItem<'a>>,
+        selected: usize,
+    },
+    // one of several named options, stepped through with the arrows either side
+    Choice {
+        options: Vec<String>,
+        selected: usize,
+        text: Text<'a>,
+    },
+}
+
+impl Kind<'_> {
+    // moves a choice `step` options along, wrapping around at either end
+    fn step_choice(&mut self, step: i32) {
+        if let Kind::Choice {
+            options, selected, ..
+        } = self
+        {
+            let count = options.len().max(1) as i32;
+            *selected = (*selected as i32 + step).rem_euclid(count) as usize;
+            self.refresh_choice();
+        }
+    }
+
+    fn refresh_choice(&mut self) {
+        if let Kind::Choice {
+            options,
+            selected,
+            text,
+        } = self
+        {
+            let option = options.get(*selected).map_or("", String::as_str);
+            text.set_string(&format!("<  {}  >", option));
+            center_origin(text);
+        }
+    }
+}
+
+struct Widget<'a, W> {
+    id: W,
+    // widgets are laid out around their centre
+    position: Vector2f,
+    kind: Kind<'a>,
+}
+
+impl<W> Widget<'_, W> {
+    fn focusable(&self) -> bool {
+        !matches!(self.kind, Kind::Label { .. })
+    }
+
+    fn bounds(&self) -> FloatRect {
+        let centered = |size: Vector2f| {
+            FloatRect::new(
+                self.position.x - size.x / 2.0,
+                self.position.y - size.y / 2.0,
+                size.x,
+                size.y,
+            )
+        };
+        match &self.kind {
+            Kind::Label { graphic, .. } | Kind::Button { graphic, .. } => graphic.global_bounds(),
+            Kind::Choice { .. } => centered(CHOICE_SIZE),
+            Kind::Slider { .. } => centered(Vector2f::new(SLIDER_WIDTH + KNOB_SIZE.x, KNOB_SIZE.y)),
+            Kind::Toggle { .. } => centered(Vector2f::new(TOGGLE_SIZE, TOGGLE_SIZE)),
+            Kind::List { items, .. } => centered(Vector2f::new(
+                TAB_SPACING * (items.len() as f32 - 1.0) + TAB_SIZE.x,
+                TAB_SIZE.y,
+            )),
+        }
+    }
+
+    fn item_at(&self, point: Vector2f) -> Option<usize> {
+        let Kind::List { items, .. } = &self.kind else {
+            return None;
+        };
+        (0..items.len()).find(|&i| {
+            let center = tab_center(self.position, i, items.len());
+            FloatRect::new(
+                center.x - TAB_SIZE.x / 2.0,
+                center.y - TAB_SIZE.y / 2.0,
+                TAB_SIZE.x,
+                TAB_SIZE.y,
+            )
+            .contains(point)
+        })
+    }
+
+    fn slider_value_at(&self, point: Vector2f) -> f32 {
+        ((point.x - (self.position.x - SLIDER_WIDTH / 2.0)) / SLIDER_WIDTH).clamp(0.0, 1.0)
+    }
+}
+
+// A screen's worth of widgets. Menus are declared once with the builder
+// methods, fed the window's events and asked to draw; `handle_event` returns
+// the id of whatever was clicked or changed so the caller can react, and the
+// current values are read back by id. `W` is usually a small enum per menu.
+pub struct Menu<'a, W> {
+    widgets: Vec<Widget<'a, W>>,
+    focused: Option<usize>,
+    // set by keyboard or gamepad navigation, the mouse only moves the focus
+    // while it is over something
+    keyboard_focus: bool,
+    dragging: Option<usize>,
+    mouse: Vector2f,
+    rect: RectangleShape<'a>,
+    icon: Sprite<'a>,
+}
+
+impl<'a, W: Copy + PartialEq> Menu<'a, W> {
+    pub fn new() -> Self {
+        Menu {
+            widgets: Vec::new(),
+            focused: None,
+            keyboard_focus: false,
+            dragging: None,
+            mouse: Vector2f::new(-1.0, -1.0),
+            rect: RectangleShape::new(),
+            icon: Sprite::new(),
+        }
+    }
+
+    fn push(mut self, id: W, position: Vector2f, kind: Kind<'a>) -> Self {
+        self.widgets.push(Widget { id, position, kind });
+        self
+    }
+
+    pub fn text_label(
+        self,
+        id: W,
+        position: Vector2f,
+        string: &str,
+        font: &'a Font,
+        size: u32,
+    ) -> Self {
+        let graphic = Graphic::text(string, font, size, position);
+        self.push_label(id, position, graphic, 1.0)
+    }
+
+    fn push_label(self, id: W, position: Vector2f, mut graphic: Graphic<'a>, scale: f32) -> Self {
+        graphic.set_scale(scale);
+        self.push(
+            id,
+            position,
+            Kind::Label {
+                graphic,
+                scale,
+                hover_scale: scale,
+                current: Tween::at(scale),
+            },
+        )
+    }
+
+    // makes the last label or button grow to `hover_scale` when hovered
+    pub fn hover_scale(mut self, hover_scale: f32) -> Self {
+        if let Some(Widget {
+            kind: Kind::Label { hover_scale: h, .. } | Kind::Button { hover_scale: h, .. },
+            ..
+        }) = self.widgets.last_mut()
+        {
+            *h = hover_scale;
+        }
+        self
+    }
+
+    // colours the last text label or button
+    pub fn text_color(mut self, new_color: Color) -> Self {
+        if let Some(Widget {
+            kind:
+                Kind::Label {
+                    graphic: Graphic::Text { color, .. },
+                    ..
+                }
+                | Kind::Button {
+                    graphic: Graphic::Text { color, .. },
+                    ..
+                },
+            ..
+        }) = self.widgets.last_mut()
+        {
+            *color = new_color;
+        }
+        self
+    }
+
+    pub fn button(
+        self,
+        id: W,
+        position: Vector2f,
+        texture: &'a Texture,
+        hover_texture: &'a Texture,
+        scale: f32,
+    ) -> Self {
+        let graphic = Graphic::picture(texture, hover_texture, position);
+        self.push_button(id, position, graphic, scale)
+    }
+
+    pub fn text_button(
+        self,
+        id: W,
+        position: Vector2f,
+        string: &str,
+        font: &'a Font,
+        size: u32,
+    ) -> Self {
+        let graphic = Graphic::text(string, font, size, position);
+        self.push_button(id, position, graphic, 1.0)
+    }
+
+    fn push_button(self, id: W, position: Vector2f, mut graphic: Graphic<'a>, scale: f32) -> Self {
+        graphic.set_scale(scale);
+        self.push(
+            id,
+            position,
+            Kind::Button {
+                graphic,
+                scale,
+                hover_scale: scale,
+                current: Tween::at(scale),
+            },
+        )
+    }
+
+    pub fn slider(self, id: W, position: Vector2f, value: f32) -> Self {
+        let value = value.clamp(0.0, 1.0);
+        self.push(id, position, Kind::Slider { value })
+    }
+
+    pub fn toggle(self, id: W, position: Vector2f, on: bool) -> Self {
+        self.push(id, position, Kind::Toggle { on })
+    }
+
+    pub fn list(
+        self,
+        id: W,
+        position: Vector2f,
+        items: Vec<ListItem<'a>>,
+        selected: usize,
+    ) -> Self {
+        self.push(id, position, Kind::List { items, selected })
+    }
+
+    pub fn choice(
+        self,
+        id: W,
+        position: Vector2f,
+        options: Vec<String>,
+        selected: usize,
+        font: &'a Font,
+    ) -> Self {
+        let mut text = Text::new("", font, CHOICE_TEXT_SIZE);
+        text.set_position(position);
+        let selected = selected.min(options.len().saturating_sub(1));
+        let mut kind = Kind::Choice {
+            options,
+            selected,
+            text,
+        };
+        kind.refresh_choice();
+        self.push(id, position, kind)
+    }
+
+    fn find(&mut self, id: W) -> &mut Kind<'a> {
+        let widget = self.widgets.iter_mut().find(|w| w.id == id);
+        &mut widget.expect("No widget with that id").kind
+    }
+
+    pub fn value(&mut self, id: W) -> f32 {
+        match self.find(id) {
+            Kind::Slider { value } => *value,
+            _ => 0.0,
+        }
+    }
+
+    pub fn is_on(&mut self, id: W) -> bool {
+        matches!(self.find(id), Kind::Toggle { on: true })
+    }
+
+    pub fn set_on(&mut self, id: W, new_on: bool) {
+        if let Kind::Toggle { on } = self.find(id) {
+            *on = new_on;
+        }
+    }
+
+    pub fn selected(&mut self, id: W) -> usize {
+        match self.find(id) {
+            Kind::List { selected, .. } | Kind::Choice { selected, .. } => *selected,
+            _ => 0,
+        }
+    }
+
+    // puts the keyboard focus on `id`, e.g. after rebuilding a menu under the player
+    pub fn focus(&mut self, id: W) {
+        self.focused = self.widgets.iter().position(|w| w.id == id);
+        self.keyboard_focus = true;
+    }
+
+    fn widget_at(&self, point: Vector2f) -> Option<usize> {
+        self.widgets
+            .iter()
+            .position(|w| w.focusable() && w.bounds().contains(point))
+    }
+
+    // `to_view` maps window pixels into the view the menu is drawn in
+    pub fn handle_event(
+        &mut self,
+        event: &Event,
+        to_view: impl Fn(Vector2i) -> Vector2f,
+    ) -> Option<W> {
+        match *event {
+            Event::MouseMoved { x, y } => {
+                self.mouse = to_view(Vector2i::new(x, y));
+                if let Some(i) = self.dragging {
+                    return self.drag(i);
+                }
+                let hovered = self.widget_at(self.mouse);
+                if hovered.is_some() || !self.keyboard_focus {
+                    self.focused = hovered;
+                    self.keyboard_focus = false;
+                }
+                None
+            }
+            Event::MouseButtonPressed {
+                button: mouse::Button::Left,
+                x,
+                y,
+            } => {
+                self.mouse = to_view(Vector2i::new(x, y));
+                let i = self.widget_at(self.mouse)?;
+                self.focused = Some(i);
+                if let Kind::Slider { .. } = self.widgets[i].kind {
+                    self.dragging = Some(i);
+                    return self.drag(i);
+                }
+                None
+            }
+            Event::MouseButtonReleased {
+                button: mouse::Button::Left,
+                x,
+                y,
+            } => {
+                self.mouse = to_view(Vector2i::new(x, y));
+                // a drag already reported its changes as it went
+                if self.dragging.take().is_some() {
+                    return None;
+                }
+                let i = self.widget_at(self.mouse)?;
+                let item = self.widgets[i].item_at(self.mouse);
+                let left_half = self.mouse.x < self.widgets[i].position.x;
+                let widget = &mut self.widgets[i];
+                match &mut widget.kind {
+                    Kind::Choice { .. } => {
+                        widget.kind.step_choice(if left_half { -1 } else { 1 });
+                        Some(widget.id)
+                    }
+                    Kind::Button { .. } => Some(widget.id),
+                    Kind::Toggle { on } => {
+                        *on = !*on;
+                        Some(widget.id)
+                    }
+                    Kind::List { selected, .. } => {
+                        *selected = item?;
+                        Some(widget.id)
+                    }
+                    Kind::Label { .. } | Kind::Slider { .. } => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Moves the focus or works the focused widget. Returns the id of whatever
+    // was activated or changed, like `handle_event`; Back is left to the caller.
+    pub fn navigate(&mut self, action: Action) -> Option<W> {
+        self.keyboard_focus = true;
+        let Some(i) = self.focused else {
+            self.focused = self.widgets.iter().position(|w| w.focusable());
+            return None;
+        };
+        let widget = &mut self.widgets[i];
+        let step = match action {
+            Action::Left => -1,
+            Action::Right => 1,
+            _ => 0,
+        };
+        match (&mut widget.kind, action) {
+            (Kind::Slider { value }, Action::Left | Action::Right) => {
+                *value = (*value + SLIDER_STEP * step as f32).clamp(0.0, 1.0);
+                Some(widget.id)
+            }
+            (Kind::List { items, selected }, Action::Left | Action::Right) => {
+                if items.is_empty() {
+                    return None;
+                }
+                let next = (*selected as i32 + step).clamp(0, items.len() as i32 - 1) as usize;
+                if next == *selected {
+                    return None;
+                }
+                *selected = next;
+                Some(widget.id)
+            }
+            (Kind::Choice { .. }, Action::Left | Action::Right | Action::Confirm) => {
+                widget.kind.step_choice(if step == 0 { 1 } else { step });
+                Some(widget.id)
+            }
+            (Kind::Button { .. }, Action::Confirm) => Some(widget.id),
+            (Kind::Toggle { on }, Action::Confirm) => {
+                *on = !*on;
+                Some(widget.id)
+            }
+            (_, Action::Up | Action::Down | Action::Left | Action::Right) => {
+                if let Some(next) = self.neighbour(i, action) {
+                    self.focused = Some(next);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    // the closest focusable widget in `direction`, favouring ones straight in
+    // line; going up from the top or down from the bottom wraps to the other end
+    fn neighbour(&self, from: usize, direction: Action) -> Option<usize> {
+        let center = |w: &Widget<W>| {
+            let b = w.bounds();
+            Vector2f::new(b.left + b.width / 2.0, b.top + b.height / 2.0)
+        };
+        let start = center(&self.widgets[from]);
+        let along_across = |w: &Widget<W>| {
+            let d = center(w) - start;
+            match direction {
+                Action::Up => (-d.y, d.x),
+                Action::Down => (d.y, d.x),
+                Action::Left => (-d.x, d.y),
+                _ => (d.x, d.y),
+            }
+        };
+        let candidates = || {
+            self.widgets
+                .iter()
+                .enumerate()
+                .filter(|(i, w)| *i != from && w.focusable())
+                .map(|(i, w)| (i, along_across(w)))
+        };
+        let closest = candidates()
+            .filter(|(_, (along, _))| *along > 1.0)
+            .map(|(i, (along, across))| (i, along + across.abs() * 2.0))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let wraps = matches!(direction, Action::Up | Action::Down);
+        let farthest_back = || {
+            candidates()
+                .filter(|(_, (along, _))| *along < -1.0)
+                .map(|(i, (along, across))| (i, along + across.abs() * 2.0))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+        };
+        closest
+            .or_else(|| if wraps { farthest_back() } else { None })
+            .map(|(i, _)| i)
+    }
+
+    fn drag(&mut self, i: usize) -> Option<W> {
+        let new_value = self.widgets[i].slider_value_at(self.mouse);
+        let widget = &mut self.widgets[i];
+        if let Kind::Slider { value } = &mut widget.kind
+            && *value != new_value
+        {
+            *value = new_value;
+            return Some(widget.id);
+        }
+        None
+    }
+
+    // eases labels and buttons towards their hover scale
+    pub fn update(&mut self, dt: f32) {
+        for i in 0..self.widgets.len() {
+            let grow = match self.widgets[i].kind {
+                Kind::Label { .. } => self.widgets[i].bounds().contains(self.mouse),
+                _ => self.focused == Some(i),
+            };
+            if let Kind::Label {
+                scale,
+                hover_scale,
+                current,
+                ..
+            }
+            | Kind::Button {
+                scale,
+                hover_scale,
+                current,
+                ..
+            } = &mut self.widgets[i].kind
+            {
+                let target = if grow { *hover_scale } else { *scale };
+                if current.to != target {
+                    current.retarget(target, HOVER_TIME, Ease::OutCubic);
+                }
+                current.update(dt);
+            }
+        }
+    }
+
+    pub fn draw(&mut self, window: &mut RenderWindow) {
+        for i in 0..self.widgets.len() {
+            let focused = self.focused == Some(i);
+            let hovered_item = self.widgets[i].item_at(self.mouse);
+            let widget = &mut self.widgets[i];
+            let position = widget.position;
+            match &mut widget.kind {
+                Kind::Label {
+                    graphic, current, ..
+                } => {
+                    graphic.set_scale(current.value());
+                    graphic.draw(window, false);
+                }
+                Kind::Button {
+                    graphic, current, ..
+                } => {
+                    graphic.set_scale(current.value());
+                    graphic.draw(window, focused);
+                    // without a mouse pointer on it the growth alone is easy to miss
+                    if focused && self.keyboard_focus {
+                        let b = graphic.global_bounds();
+                        draw_rect(
+                            &mut self.rect,
+                            window,
+                            Vector2f::new(b.left - FOCUS_PADDING, b.top - FOCUS_PADDING),
+                            Vector2f::new(b.width, b.height)
+                                + Vector2f::new(FOCUS_PADDING, FOCUS_PADDING) * 2.0,
+                            Color::TRANSPARENT,
+                            Some(FOCUS_COLOR),
+                        );
+                    }
+                }
+                Kind::Slider { value } => {
+                    let left = position.x - SLIDER_WIDTH / 2.0;
+                    let value = *value;
+                    draw_rect(
+                        &mut self.rect,
+                        window,
+                        Vector2f::new(left, position.y - SLIDER_HEIGHT / 2.0),
+                        Vector2f::new(SLIDER_WIDTH, SLIDER_HEIGHT),
+                        Color::rgb(60, 60, 70),
+                        None,
+                    );
+                    draw_rect(
+                        &mut self.rect,
+                        window,
+                        Vector2f::new(left, position.y - SLIDER_HEIGHT / 2.0),
+                        Vector2f::new(SLIDER_WIDTH * value, SLIDER_HEIGHT),
+                        FILL_COLOR,
+                        None,
+                    );
+                    draw_rect(
+                        &mut self.rect,
+                        window,
+                        Vector2f::new(left + SLIDER_WIDTH * value, position.y) - KNOB_SIZE / 2.0,
+                        KNOB_SIZE,
+                        Color::WHITE,
+                        focused.then_some(FOCUS_COLOR),
+                    );
+                }
+                Kind::Choice { text, .. } => {
+                    text.set_fill_color(if focused { FOCUS_COLOR } else { Color::WHITE });
+                    window.draw(text);
+                }
+                Kind::Toggle { on } => {
+                    let size = Vector2f::new(TOGGLE_SIZE, TOGGLE_SIZE);
+                    draw_rect(
+                        &mut self.rect,
+                        window,
+                        position - size / 2.0,
+                        size,
+                        Color::rgb(30, 30, 40),
+                        Some(if focused { FOCUS_COLOR } else { Color::WHITE }),
+                    );
+                    if *on {
+                        draw_rect(
+                            &mut self.rect,
+                            window,
+                            position - size / 4.0,
+                            size / 2.0,
+                            FILL_COLOR,
+                            None,
+                        );
+                    }
+                }
+                Kind::List { items, selected } => {
+                    for (index, item) in items.iter().enumerate() {
+                        let center = tab_center(position, index, items.len());
+                        let is_selected = index == *selected;
+                        let grow = if hovered_item == Some(index) {
+                            1.1
+                        } else {
+                            1.0
+                        };
+                        let size = TAB_SIZE * grow;
+                        let (fill, outline) = if is_selected {
+                            (item.color, Color::WHITE)
+                        } else {
+                            let c = item.color;
+                            (Color::rgba(c.r / 3, c.g / 3, c.b / 3, 220), c)
+                        };
+                        let outline = if focused && is_selected {
+                            FOCUS_COLOR
+                        } else {
+                            outline
+                        };
+                        draw_rect(
+                            &mut self.rect,
+                            window,
+                            center - size / 2.0,
+                            size,
+                            fill,
+                            Some(outline),
+                        );
+
+                        if let Some(icon) = item.icon {
+                            let icon_size = icon.size();
+                            self.icon.set_texture(icon, true);
+                            self.icon.set_origin(Vector2f::new(
+                                icon_size.x as f32 / 2.0,
+                                icon_size.y as f32 / 2.0,
+                            ));
+                            self.icon.set_scale(TAB_SIZE.y * 0.75 / icon_size.y as f32);
+                            self.icon.set_position(center);
+                            window.draw(&self.icon);
+                        }
+                        let first_x = center.x - (item.pips as f32 - 1.0) * PIP_SIZE;
+                        for pip in 0..item.pips {
+                            let pip_center =
+                                Vector2f::new(first_x + pip as f32 * PIP_SIZE * 2.0, center.y);
+                            draw_rect(
+                                &mut self.rect,
+                                window,
+                                pip_center - Vector2f::new(PIP_SIZE, PIP_SIZE) / 2.0,
+                                Vector2f::new(PIP_SIZE, PIP_SIZE),
+                                Color::WHITE,
+                                None,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tab_center(position: Vector2f, index: usize, count: usize) -> Vector2f {
+    let first = position.x - TAB_SPACING * (count as f32 - 1.0) / 2.0;
+    Vector2f::new(first + TAB_SPACING * index as f32, position.y)
+}
+
+fn draw_rect(
+    rect: &mut RectangleShape,
+    window: &mut RenderWindow,
+    position: Vector2f,
+    size: Vector2f,
+    fill: Color,
+    outline: Option<Color>,
+) {
+    rect.set_position(position);
+    rect.set_size(size);
+    rect.set_fill_color(fill);
+    match outline {
+        Some(color) => {
+            rect.set_outline_color(color);
+            rect.set_outline_thickness(2.0);
+        }
+        None => rect.set_outline_thickness(0.0),
+    }
+    window.draw(rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum Item {
+        Volume,
+        Shake,
+        Mono,
+        Bars,
+    }
+
+    // a column of widgets 80 apart, with a second one off to the right
+    fn menu() -> Menu<'static, Item> {
+        Menu::new()
+            .slider(Item::Volume, Vector2f::new(400.0, 100.0), 0.5)
+            .toggle(Item::Shake, Vector2f::new(400.0, 180.0), false)
+            .toggle(Item::Mono, Vector2f::new(400.0, 260.0), true)
+            .toggle(Item::Bars, Vector2f::new(600.0, 260.0), false)
+    }
+
+    fn focused(menu: &Menu<Item>) -> Option<Item> {
+        menu.focused.map(|i| menu.widgets[i].id)
+    }
+
+    #[test]
+    fn the_first_press_focuses_the_first_widget() {
+        let mut menu = menu();
+        assert_eq!(menu.navigate(Action::Down), None);
+        assert_eq!(focused(&menu), Some(Item::Volume));
+    }
+
+    #[test]
+    fn focus_moves_down_the_column_and_wraps() {
+        let mut menu = menu();
+        menu.focus(Item::Shake);
+        menu.navigate(Action::Down);
+        assert_eq!(focused(&menu), Some(Item::Mono));
+        menu.navigate(Action::Down);
+        assert_eq!(focused(&menu), Some(Item::Volume));
+        menu.navigate(Action::Up);
+        assert_eq!(focused(&menu), Some(Item::Mono));
+    }
+
+    #[test]
+    fn sideways_moves_stop_at_the_edge() {
+        let mut menu = menu();
+        menu.focus(Item::Mono);
+        menu.navigate(Action::Right);
+        assert_eq!(focused(&menu), Some(Item::Bars));
+        menu.navigate(Action::Right);
+        assert_eq!(focused(&menu), Some(Item::Bars));
+        menu.navigate(Action::Left);
+        assert_eq!(focused(&menu), Some(Item::Mono));
+    }
+
+    #[test]
+    fn neighbours_in_line_beat_closer_ones_off_to_the_side() {
+        let mut menu = Menu::new()
+            .toggle(Item::Volume, Vector2f::new(400.0, 100.0), false)
+            .toggle(Item::Shake, Vector2f::new(470.0, 160.0), false)
+            .toggle(Item::Mono, Vector2f::new(400.0, 220.0), false)
+            .toggle(Item::Bars, Vector2f::new(400.0, 50.0), false);
+        menu.focus(Item::Volume);
+        menu.navigate(Action::Down);
+        assert_eq!(focused(&menu), Some(Item::Mono));
+        // nothing is above the top, so up wraps round to the bottom
+        menu.focus(Item::Bars);
+        menu.navigate(Action::Up);
+        assert_eq!(focused(&menu), Some(Item::Mono));
+    }
+
+    #[test]
+    fn sliders_step_and_stop_at_the_ends() {
+        let mut menu = menu();
+        menu.focus(Item::Volume);
+        assert_eq!(menu.navigate(Action::Right), Some(Item::Volume));
+        assert!((menu.value(Item::Volume) - 0.6).abs() < 1e-6);
+        for _ in 0..10 {
+            menu.navigate(Action::Right);
+        }
+        assert_eq!(menu.value(Item::Volume), 1.0);
+        for _ in 0..20 {
+            menu.navigate(Action::Left);
+        }
+        assert_eq!(menu.value(Item::Volume), 0.0);
+        // left and right work the slider instead of moving the focus
+        assert_eq!(focused(&menu), Some(Item::Volume));
+    }
+
+    #[test]
+    fn confirm_flips_the_focused_toggle() {
+        let mut menu = menu();
+        menu.focus(Item::Mono);
+        assert_eq!(menu.navigate(Action::Confirm), Some(Item::Mono));
+        assert!(!menu.is_on(Item::Mono));
+        assert!(!menu.is_on(Item::Shake));
+    }
+
+    #[test]
+    fn an_empty_list_ignores_left_and_right() {
+        let mut menu = Menu::new().list(Item::Bars, Vector2f::new(400.0, 100.0), Vec::new(), 0);
+        menu.focus(Item::Bars);
+        assert_eq!(menu.navigate(Action::Right), None);
+        assert_eq!(menu.navigate(Action::Left), None);
+    }
+}